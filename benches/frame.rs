@@ -0,0 +1,108 @@
+//! Frame time of scenes with many small draws, where per-frame CPU work dominates.
+//!
+//! Runs on a software adapter with a tiny target and shadow map so that rasterization
+//! does not hide the cost of resource creation and uploads.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use winit::dpi::PhysicalSize;
+
+use spinny::texture::Texture;
+use spinny::vertex::Vertex;
+use spinny::{Error, Instance, Material, Mesh, Model, RenderConfig, State};
+
+const MODELS: usize = 1000;
+
+fn headless_state() -> Option<State> {
+    let state = pollster::block_on(State::new_headless(
+        PhysicalSize::new(64, 64),
+        RenderConfig {
+            force_fallback_adapter: true,
+            shadow_map_size: 64,
+            ..Default::default()
+        },
+    ));
+    return match state {
+        Ok(state) => Some(state),
+        Err(Error::NoAdapter) => {
+            eprintln!("no software adapter available, skipping frame benchmark");
+            None
+        }
+        Err(err) => panic!("{err}"),
+    };
+}
+
+fn triangle(state: &State) -> Model {
+    let vertices = [[0.0, 0.0, 0.0], [0.1, 0.0, 0.0], [0.0, 0.1, 0.0]].map(|pos| Vertex {
+        pos,
+        tex_cords: [0.0, 0.0],
+        normals: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
+    });
+    let indices = [0, 1, 2];
+    let (vertex_buffer, index_buffer) = Vertex::make_buffers(&state.device, &vertices, &indices);
+    let texture = Texture::create_solid_color_texture(&state.device, &state.queue, [255; 4]);
+    return Model {
+        meshes: vec![Mesh {
+            vertex_buffer,
+            index_buffer,
+            len_indicies: indices.len(),
+            material_id: 0,
+        }],
+        materials: vec![Material::new(
+            &state.device,
+            &state.queue,
+            &state.layouts,
+            [0.5, 0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            32.0,
+            texture,
+        )],
+    };
+}
+
+/// `MODELS` triangles spread over a grid in front of the camera, one instance each.
+fn populate(state: &mut State) {
+    for i in 0..MODELS {
+        let translation = glam::vec3(
+            (i % 32) as f32 * 0.2 - 3.2,
+            (i / 32) as f32 * 0.2 - 3.2,
+            -2.0,
+        );
+        let instance = Instance::from_translation_rotation_scale(
+            translation,
+            glam::Quat::IDENTITY,
+            glam::Vec3::ONE,
+        );
+        state.scene.add_model(triangle(state), vec![instance]);
+    }
+    state.camera.update_view_matrix();
+}
+
+fn render(state: &mut State) {
+    state.render().unwrap();
+    state.device.poll(wgpu::Maintain::Wait);
+}
+
+fn frame(c: &mut Criterion) {
+    let Some(mut state) = headless_state() else {
+        return;
+    };
+    populate(&mut state);
+    c.bench_function("static scene, 1000 models", |b| {
+        b.iter(|| render(&mut state))
+    });
+    c.bench_function("moving scene, 1000 models", |b| {
+        b.iter(|| {
+            for model in 0..MODELS {
+                state
+                    .scene
+                    .rotate_instances(model, glam::Quat::from_rotation_z(0.01));
+            }
+            render(&mut state);
+        })
+    });
+}
+
+criterion_group!(benches, frame);
+criterion_main!(benches);
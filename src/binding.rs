@@ -1,4 +1,7 @@
-use crate::{camera::CameraRaw, light::Light, texture};
+use crate::camera::CameraRaw;
+use crate::light::{Light, LightsRaw, SpotLightsRaw, SunRaw};
+use crate::shadow::ShadowParamsRaw;
+use crate::texture;
 use wgpu::{util::DeviceExt, BindingType};
 
 pub trait Bindeable {
@@ -23,7 +26,7 @@ impl Binding {
             buffers.push(
                 device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: None,
-                    contents: *resource,
+                    contents: resource,
                     usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 }),
             );
@@ -67,26 +70,73 @@ impl TextureBinding {
         resource: &texture::Texture,
         bind_index: u32,
     ) -> TextureBinding {
-        return TextureBinding {
-            bind_group: device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: None,
-                layout,
-                entries: &[
+        return TextureBinding::with_textures(device, layout, &[resource], bind_index);
+    }
+
+    /// Binds texture `i` at `2 * i` and its sampler at `2 * i + 1`, matching
+    /// `TextureBinding::bind_group_layout`.
+    pub fn with_textures(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        resources: &[&texture::Texture],
+        bind_index: u32,
+    ) -> TextureBinding {
+        let entries: Vec<wgpu::BindGroupEntry> = resources
+            .iter()
+            .enumerate()
+            .flat_map(|(i, resource)| {
+                [
                     wgpu::BindGroupEntry {
-                        binding: 0,
+                        binding: 2 * i as u32,
                         resource: wgpu::BindingResource::TextureView(&resource.texture_view),
                     },
                     wgpu::BindGroupEntry {
-                        binding: 1,
+                        binding: 2 * i as u32 + 1,
                         resource: wgpu::BindingResource::Sampler(&resource.sampler),
                     },
-                ],
+                ]
+            })
+            .collect();
+        return TextureBinding {
+            bind_group: device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout,
+                entries: &entries,
             }),
             bind_index,
         };
     }
 
-    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    /// Layout of `textures` filterable textures, each followed by its sampler.
+    pub fn bind_group_layout(device: &wgpu::Device, textures: u32) -> wgpu::BindGroupLayout {
+        let entries: Vec<wgpu::BindGroupLayoutEntry> = (0..textures)
+            .flat_map(|i| {
+                [
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * i,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2 * i + 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ]
+            })
+            .collect();
+        return device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &entries,
+        });
+    }
+    pub fn shadow_texture_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         return device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
@@ -94,8 +144,8 @@ impl TextureBinding {
                     binding: 0,
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::CubeArray,
                         multisampled: false,
                     },
                     count: None,
@@ -103,30 +153,38 @@ impl TextureBinding {
                 wgpu::BindGroupLayoutEntry {
                     binding: 1,
                     visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
                     count: None,
                 },
-            ],
-        });
-    }
-    pub fn shadow_texture_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-        return device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[
                 wgpu::BindGroupLayoutEntry {
-                    binding: 0,
+                    binding: 2,
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
                         sample_type: wgpu::TextureSampleType::Depth,
-                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
                         multisampled: false,
                     },
                     count: None,
                 },
                 wgpu::BindGroupLayoutEntry {
-                    binding: 1,
+                    binding: 3,
                     visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // ambient occlusion, read per pixel
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
                     count: None,
                 },
             ],
@@ -134,6 +192,9 @@ impl TextureBinding {
     }
 }
 
+/// Camera (`CameraRaw`), light list (`LightsRaw`), directional light (`SunRaw`), spot light
+/// list (`SpotLightsRaw`) and shadow filtering (`ShadowParamsRaw`) uniforms shared by the main
+/// and light pipelines.
 pub struct Globals {
     pub camera_raw: CameraRaw,
     pub light: Light,
@@ -150,7 +211,7 @@ impl Bindeable for Globals {
                     ty: BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
-                        min_binding_size: wgpu::BufferSize::new(80),
+                        min_binding_size: wgpu::BufferSize::new(96),
                     },
                     count: None,
                 },
@@ -160,7 +221,45 @@ impl Bindeable for Globals {
                     ty: BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
-                        min_binding_size: wgpu::BufferSize::new(96),
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<LightsRaw>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<SunRaw>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<SpotLightsRaw>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<ShadowParamsRaw>() as u64,
+                        ),
                     },
                     count: None,
                 },
@@ -0,0 +1,364 @@
+use crate::texture::Texture;
+
+/// Most levels of the blur chain, the first one is half the size of the frame.
+const MAX_LEVELS: u32 = 6;
+
+/// Bloom settings, they can be changed between frames.
+#[derive(Copy, Clone, Debug)]
+pub struct BloomConfig {
+    pub enabled: bool,
+    /// Brightness above which colours start to bloom, with a soft knee below it.
+    pub threshold: f32,
+    /// Strength the blurred highlights are added back to the frame with.
+    pub intensity: f32,
+    /// Spread of every upsampling step in texels, larger values give a wider glow.
+    pub radius: f32,
+}
+
+impl Default for BloomConfig {
+    fn default() -> Self {
+        return BloomConfig {
+            enabled: false,
+            threshold: 1.0,
+            intensity: 0.1,
+            radius: 1.0,
+        };
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BloomParams {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    radius: f32,
+}
+
+/// Post-processing pass adding a glow around the bright parts of an HDR texture.
+/// The highlights are thresholded into a half size mip chain, blurred by progressively
+/// downsampling and upsampling it, and added back onto the input.
+pub struct Bloom {
+    pub prefilter_pipeline: wgpu::RenderPipeline,
+    pub downsample_pipeline: wgpu::RenderPipeline,
+    pub upsample_pipeline: wgpu::RenderPipeline,
+    pub composite_pipeline: wgpu::RenderPipeline,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub sampler: wgpu::Sampler,
+    pub params_buffer: wgpu::Buffer,
+    /// Mip chain the blur runs through.
+    pub chain: wgpu::Texture,
+    /// One view per level of `chain`.
+    pub levels: Vec<wgpu::TextureView>,
+    /// `levels[i]` as a shader input, at index `i`.
+    pub level_bind_groups: Vec<wgpu::BindGroup>,
+    /// The frame being bloomed, as input of the first downsample.
+    pub input_bind_group: wgpu::BindGroup,
+}
+
+impl Bloom {
+    /// Creates the pass for `input`, its format has to be blendable.
+    pub fn new(device: &wgpu::Device, input: &Texture) -> Self {
+        let format = input.texture.format();
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<BloomParams>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<BloomParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/bloom.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+        };
+        let create_pipeline = |entry_point, blend| {
+            return device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some(entry_point),
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+        };
+        let prefilter_pipeline = create_pipeline("fs_prefilter", None);
+        let downsample_pipeline = create_pipeline("fs_downsample", None);
+        let upsample_pipeline = create_pipeline("fs_upsample", Some(additive));
+        let composite_pipeline = create_pipeline("fs_composite", Some(additive));
+
+        let (chain, levels) = Bloom::create_chain(device, input);
+        let level_bind_groups = levels
+            .iter()
+            .map(|level| {
+                Bloom::create_bind_group(
+                    device,
+                    &bind_group_layout,
+                    level,
+                    &sampler,
+                    &params_buffer,
+                )
+            })
+            .collect();
+        let input_bind_group = Bloom::create_bind_group(
+            device,
+            &bind_group_layout,
+            &input.texture_view,
+            &sampler,
+            &params_buffer,
+        );
+        return Bloom {
+            prefilter_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            composite_pipeline,
+            bind_group_layout,
+            sampler,
+            params_buffer,
+            chain,
+            levels,
+            level_bind_groups,
+            input_bind_group,
+        };
+    }
+
+    fn create_chain(
+        device: &wgpu::Device,
+        input: &Texture,
+    ) -> (wgpu::Texture, Vec<wgpu::TextureView>) {
+        let size = input.texture.size();
+        let width = (size.width / 2).max(1);
+        let height = (size.height / 2).max(1);
+        // stop before levels get smaller than a couple of texels
+        let level_count = (width.min(height).max(2).ilog2()).clamp(1, MAX_LEVELS);
+        let chain = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: input.texture.format(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let levels = (0..level_count)
+            .map(|level| {
+                chain.create_view(&wgpu::TextureViewDescriptor {
+                    base_mip_level: level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        return (chain, levels);
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        params_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        return device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+    }
+
+    /// Rebuilds the mip chain for a new input texture, after the render target was resized.
+    pub fn set_input(&mut self, device: &wgpu::Device, input: &Texture) {
+        let (chain, levels) = Bloom::create_chain(device, input);
+        self.level_bind_groups = levels
+            .iter()
+            .map(|level| {
+                Bloom::create_bind_group(
+                    device,
+                    &self.bind_group_layout,
+                    level,
+                    &self.sampler,
+                    &self.params_buffer,
+                )
+            })
+            .collect();
+        self.input_bind_group = Bloom::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &input.texture_view,
+            &self.sampler,
+            &self.params_buffer,
+        );
+        self.chain = chain;
+        self.levels = levels;
+    }
+
+    /// Blooms `input`, the texture the pass was created or last resized with, in place.
+    pub fn apply(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        input: &Texture,
+        config: &BloomConfig,
+    ) {
+        let params = BloomParams {
+            threshold: config.threshold,
+            knee: config.threshold * 0.5,
+            intensity: config.intensity,
+            radius: config.radius,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+
+        Bloom::blit(
+            encoder,
+            &self.prefilter_pipeline,
+            &self.input_bind_group,
+            &self.levels[0],
+            true,
+        );
+        for level in 1..self.levels.len() {
+            Bloom::blit(
+                encoder,
+                &self.downsample_pipeline,
+                &self.level_bind_groups[level - 1],
+                &self.levels[level],
+                true,
+            );
+        }
+        for level in (1..self.levels.len()).rev() {
+            Bloom::blit(
+                encoder,
+                &self.upsample_pipeline,
+                &self.level_bind_groups[level],
+                &self.levels[level - 1],
+                false,
+            );
+        }
+        Bloom::blit(
+            encoder,
+            &self.composite_pipeline,
+            &self.level_bind_groups[0],
+            &input.texture_view,
+            false,
+        );
+    }
+
+    /// Draws a fullscreen triangle reading `source` into `target`, clearing it first or
+    /// blending onto it.
+    fn blit(
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        source: &wgpu::BindGroup,
+        target: &wgpu::TextureView,
+        clear: bool,
+    ) {
+        let load = match clear {
+            true => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            false => wgpu::LoadOp::Load,
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, source, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -6,6 +6,8 @@ pub struct Camera {
     pub yaw: f32,
     pub speed: f32,
     pub perspective: glam::f32::Mat4,
+    pub fov: f32,
+    pub aspect_ratio: f32,
     pub position: glam::f32::Vec3,
     pub view_matrix: glam::f32::Mat4,
 }
@@ -15,6 +17,7 @@ pub struct Camera {
 pub struct CameraRaw {
     pub view_matrix: [[f32; 4]; 4],
     pub position: [f32; 4],
+    pub direction: [f32; 4],
 }
 
 impl Bindeable for CameraRaw {
@@ -27,7 +30,7 @@ impl Bindeable for CameraRaw {
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
-                    min_binding_size: wgpu::BufferSize::new(80),
+                    min_binding_size: wgpu::BufferSize::new(96),
                 },
                 count: None,
             }],
@@ -35,6 +38,15 @@ impl Bindeable for CameraRaw {
     }
 }
 
+/// Directions the camera can be moved in, relative to where it is looking.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CameraMovement {
+    Forward,
+    Backward,
+    Left,
+    Right,
+}
+
 pub const CAMERA_NEAR_PLANE: f32 = 0.1;
 pub const CAMERA_FAR_PLANE: f32 = 100.0;
 
@@ -68,16 +80,42 @@ impl Camera {
             yaw,
             speed,
             perspective,
+            fov,
+            aspect_ratio,
         };
     }
 
-    pub fn update_view_matrix(&mut self) {
-        if self.pitch > 89.0 {
-            self.pitch = 89.0;
-        }
-        if self.pitch < -89.0 {
-            self.pitch = -89.0;
+    /// Rebuilds the perspective projection, needed whenever the render target is resized.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.aspect_ratio = aspect_ratio;
+        self.perspective = glam::f32::Mat4::perspective_rh(
+            self.fov,
+            aspect_ratio,
+            CAMERA_NEAR_PLANE,
+            CAMERA_FAR_PLANE,
+        );
+        self.update_view_matrix();
+    }
+
+    pub fn move_by(&mut self, movement: CameraMovement, delta_time: f32) {
+        let step = self.speed * delta_time;
+        let left = glam::Vec3::Y.cross(self.direction);
+        match movement {
+            CameraMovement::Forward => self.position += self.direction * step,
+            CameraMovement::Backward => self.position -= self.direction * step,
+            CameraMovement::Left => self.position += left * step,
+            CameraMovement::Right => self.position -= left * step,
         }
+    }
+
+    /// Turns the camera by a mouse delta in pixels, yaw follows x and pitch follows y.
+    pub fn look_by(&mut self, delta_mouse: (f64, f64), delta_time: f32) {
+        self.yaw += delta_mouse.0 as f32 * self.speed * delta_time * 0.05;
+        self.pitch -= delta_mouse.1 as f32 * self.speed * delta_time * 0.05;
+    }
+
+    pub fn update_view_matrix(&mut self) {
+        self.pitch = self.pitch.clamp(-89.0, 89.0);
         let direction = Camera::direction(self.pitch, self.yaw);
         let look_at =
             glam::f32::Mat4::look_at_rh(self.position, self.position + direction, glam::Vec3::Y);
@@ -85,10 +123,11 @@ impl Camera {
         self.view_matrix = self.perspective * look_at;
     }
 
-    pub fn to_camera_raw(&mut self) -> CameraRaw {
+    pub fn to_camera_raw(&self) -> CameraRaw {
         return CameraRaw {
             view_matrix: self.view_matrix.to_cols_array_2d(),
             position: [self.position.x, self.position.y, self.position.z, 0.0],
+            direction: [self.direction.x, self.direction.y, self.direction.z, 0.0],
         };
     }
 }
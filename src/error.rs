@@ -0,0 +1,114 @@
+use std::fmt;
+use std::path::PathBuf;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// An asset or scene file could not be read.
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// A Wavefront OBJ file could not be parsed.
+    Obj {
+        path: PathBuf,
+        source: tobj::LoadError,
+    },
+    /// A glTF file or one of its buffers or images could not be loaded.
+    Gltf {
+        path: PathBuf,
+        source: gltf::Error,
+    },
+    /// A texture could not be opened or decoded.
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    /// A scene file is malformed, `line` and `column` point at the offending token.
+    Scene {
+        path: Option<PathBuf>,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// No adapter matched the requested backends and options.
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    CreateSurface(wgpu::CreateSurfaceError),
+    /// Acquiring a surface texture failed in a way that reconfiguring cannot fix.
+    Surface(wgpu::SurfaceError),
+    /// Reading a rendered frame back from the GPU failed.
+    BufferMap(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Obj { path, source } => {
+                write!(f, "{}: failed to load obj: {}", path.display(), source)
+            }
+            Error::Gltf { path, source } => {
+                write!(f, "{}: failed to load gltf: {}", path.display(), source)
+            }
+            Error::Image { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Scene {
+                path,
+                line,
+                column,
+                message,
+            } => {
+                let path = path
+                    .as_ref()
+                    .map_or("<scene>".to_owned(), |path| path.display().to_string());
+                write!(f, "{}:{}:{}: {}", path, line, column, message)
+            }
+            Error::NoAdapter => write!(f, "no suitable graphics adapter found"),
+            Error::RequestDevice(err) => write!(f, "failed to create device: {}", err),
+            Error::CreateSurface(err) => write!(f, "failed to create surface: {}", err),
+            Error::Surface(err) => write!(f, "surface error: {}", err),
+            Error::BufferMap(err) => write!(f, "failed to read back frame: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Obj { source, .. } => Some(source),
+            Error::Gltf { source, .. } => Some(source),
+            Error::Image { source, .. } => Some(source),
+            Error::Scene { .. } | Error::NoAdapter => None,
+            Error::RequestDevice(err) => Some(err),
+            Error::CreateSurface(err) => Some(err),
+            Error::Surface(err) => Some(err),
+            Error::BufferMap(err) => Some(err),
+        }
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for Error {
+    fn from(err: wgpu::RequestDeviceError) -> Self {
+        return Error::RequestDevice(err);
+    }
+}
+
+impl From<wgpu::CreateSurfaceError> for Error {
+    fn from(err: wgpu::CreateSurfaceError) -> Self {
+        return Error::CreateSurface(err);
+    }
+}
+
+impl From<wgpu::SurfaceError> for Error {
+    fn from(err: wgpu::SurfaceError) -> Self {
+        return Error::Surface(err);
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for Error {
+    fn from(err: wgpu::BufferAsyncError) -> Self {
+        return Error::BufferMap(err);
+    }
+}
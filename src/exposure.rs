@@ -0,0 +1,214 @@
+use std::time::Instant;
+
+use wgpu::util::DeviceExt;
+
+use crate::texture::Texture;
+use crate::tonemap::{ToneMapConfig, Tonemap};
+
+const HISTOGRAM_BINS: u64 = 256;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct HistogramParams {
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    adaptation: f32,
+    pixel_count: u32,
+}
+
+/// Measures the average luminance of the HDR scene with a log luminance histogram built in a
+/// compute shader, and adapts the exposure towards it over time.
+pub struct AutoExposure {
+    pub histogram_pipeline: wgpu::ComputePipeline,
+    pub average_pipeline: wgpu::ComputePipeline,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    pub params_buffer: wgpu::Buffer,
+    pub histogram_buffer: wgpu::Buffer,
+    /// Adapted average luminance and the exposure derived from it, kept between frames.
+    pub exposure_buffer: wgpu::Buffer,
+    last_update: Option<Instant>,
+}
+
+impl AutoExposure {
+    pub fn new(device: &wgpu::Device, hdr: &Texture) -> Self {
+        let storage_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<HistogramParams>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                storage_entry(2),
+                storage_entry(3),
+            ],
+        });
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<HistogramParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let histogram_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: HISTOGRAM_BINS * 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        // an average luminance of 0 makes the first measurement apply right away
+        let exposure_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[0.0f32, 1.0, 0.0, 0.0]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+        let bind_group = AutoExposure::create_bind_group(
+            device,
+            &bind_group_layout,
+            hdr,
+            &params_buffer,
+            &histogram_buffer,
+            &exposure_buffer,
+        );
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/histogram.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point| {
+            return device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: Some(&layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+        };
+        return AutoExposure {
+            histogram_pipeline: create_pipeline("build_histogram"),
+            average_pipeline: create_pipeline("average_histogram"),
+            bind_group_layout,
+            bind_group,
+            params_buffer,
+            histogram_buffer,
+            exposure_buffer,
+            last_update: None,
+        };
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        hdr: &Texture,
+        params_buffer: &wgpu::Buffer,
+        histogram_buffer: &wgpu::Buffer,
+        exposure_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        return device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&hdr.texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: histogram_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: exposure_buffer.as_entire_binding(),
+                },
+            ],
+        });
+    }
+
+    /// Points the histogram at a new HDR texture, after the render target was resized.
+    pub fn set_input(&mut self, device: &wgpu::Device, hdr: &Texture) {
+        self.bind_group = AutoExposure::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            hdr,
+            &self.params_buffer,
+            &self.histogram_buffer,
+            &self.exposure_buffer,
+        );
+    }
+
+    /// Measures the HDR input and copies the adapted exposure into `tonemap`.
+    pub fn update(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        hdr: &Texture,
+        config: &ToneMapConfig,
+        tonemap: &Tonemap,
+    ) {
+        let now = Instant::now();
+        let dt = match self.last_update {
+            Some(last_update) => (now - last_update).as_secs_f32(),
+            None => 0.0,
+        };
+        self.last_update = Some(now);
+        let size = hdr.texture.size();
+        let params = HistogramParams {
+            min_log_luminance: config.min_log_luminance,
+            log_luminance_range: (config.max_log_luminance - config.min_log_luminance).max(0.01),
+            adaptation: 1.0 - (-dt * config.adaptation_rate).exp(),
+            pixel_count: size.width * size.height,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&Default::default());
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            compute_pass.set_pipeline(&self.histogram_pipeline);
+            compute_pass.dispatch_workgroups(size.width.div_ceil(16), size.height.div_ceil(16), 1);
+            compute_pass.set_pipeline(&self.average_pipeline);
+            compute_pass.dispatch_workgroups(1, 1, 1);
+        }
+        // the tonemap pass reads the exposure as a uniform, storage buffers are not available
+        // in fragment shaders everywhere
+        encoder.copy_buffer_to_buffer(
+            &self.exposure_buffer,
+            0,
+            &tonemap.exposure_buffer,
+            0,
+            self.exposure_buffer.size(),
+        );
+    }
+}
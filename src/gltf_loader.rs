@@ -0,0 +1,412 @@
+//! glTF 2.0 import for `.gltf` and `.glb` files.
+//!
+//! Every glTF mesh becomes a `Model` with one `Mesh` per primitive. The node hierarchy is
+//! flattened: each node referencing a mesh adds an `Instance` with the node's world transform.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::instance::Instance;
+use crate::layouts::Layouts;
+use crate::mipmap::MipmapGenerator;
+use crate::model::{
+    AlphaMode, GeneratedNormals, ImportOptions, Material, MaterialParams, MaterialTextures, Mesh,
+    Model, Shading, DEFAULT_ALPHA_CUTOFF,
+};
+use crate::texture::{SamplerOptions, Texture};
+use crate::vertex::{generate_flat_normals, generate_smooth_normals, generate_tangents, Vertex};
+
+/// Whether `path` has a `.gltf` or `.glb` extension.
+pub fn is_gltf(path: &Path) -> bool {
+    return path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("gltf") || extension.eq_ignore_ascii_case("glb")
+    });
+}
+
+/// Loads a glTF file with its external or embedded buffers and images.
+pub fn load_gltf(
+    path: impl AsRef<Path>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layouts: &Layouts,
+    mipmaps: &MipmapGenerator,
+    options: &ImportOptions,
+) -> Result<Vec<(Model, Vec<Instance>)>> {
+    let path = path.as_ref();
+    let (document, buffers, images) = gltf::import(path).map_err(|source| Error::Gltf {
+        path: path.to_owned(),
+        source,
+    })?;
+    return Ok(build_models(
+        &document, &buffers, &images, device, queue, layouts, mipmaps, options,
+    ));
+}
+
+/// Loads a binary glTF (`.glb`) held in memory.
+pub fn load_gltf_slice(
+    slice: &[u8],
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layouts: &Layouts,
+    mipmaps: &MipmapGenerator,
+    options: &ImportOptions,
+) -> Result<Vec<(Model, Vec<Instance>)>> {
+    let (document, buffers, images) = gltf::import_slice(slice).map_err(|source| Error::Gltf {
+        path: "<memory>".into(),
+        source,
+    })?;
+    return Ok(build_models(
+        &document, &buffers, &images, device, queue, layouts, mipmaps, options,
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_models(
+    document: &gltf::Document,
+    buffers: &[gltf::buffer::Data],
+    images: &[gltf::image::Data],
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layouts: &Layouts,
+    mipmaps: &MipmapGenerator,
+    options: &ImportOptions,
+) -> Vec<(Model, Vec<Instance>)> {
+    let mut mesh_instances: Vec<Vec<Instance>> = vec![Vec::new(); document.meshes().len()];
+    // files without scenes are allowed, their nodes are then never drawn
+    if let Some(scene) = document.default_scene().or(document.scenes().next()) {
+        for node in scene.nodes() {
+            collect_instances(&node, glam::Mat4::IDENTITY, &mut mesh_instances);
+        }
+    }
+    let mut cache = TextureCache {
+        images: images.iter().map(to_rgba).collect(),
+        uploaded: HashMap::new(),
+        mipmaps,
+        // the mip chains of every texture are generated with a single submit
+        encoder: device.create_command_encoder(&Default::default()),
+    };
+
+    let mut models = Vec::new();
+    for (mesh, instances) in document.meshes().zip(mesh_instances) {
+        if instances.is_empty() {
+            continue;
+        }
+        let model = build_model(&mesh, buffers, &mut cache, device, queue, layouts, options);
+        models.push((model, instances));
+    }
+    queue.submit([cache.encoder.finish()]);
+    return models;
+}
+
+/// Decoded images of a file and the textures uploaded from them, so an image is uploaded once
+/// per format and sampler however many materials use it.
+struct TextureCache<'a> {
+    images: Vec<Option<image::RgbaImage>>,
+    uploaded: HashMap<(usize, wgpu::TextureFormat, SamplerOptions), Texture>,
+    mipmaps: &'a MipmapGenerator,
+    encoder: wgpu::CommandEncoder,
+}
+
+impl TextureCache<'_> {
+    /// `None` when the texture's image could not be decoded.
+    fn load(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: gltf::Texture,
+        format: wgpu::TextureFormat,
+    ) -> Option<Texture> {
+        let options = sampler_options(&texture.sampler());
+        let key = (texture.source().index(), format, options);
+        if let Some(uploaded) = self.uploaded.get(&key) {
+            return Some(uploaded.clone());
+        }
+        let image = self.images[key.0].as_ref()?;
+        let uploaded = Texture::load_texture_with_options(
+            device,
+            queue,
+            &mut self.encoder,
+            self.mipmaps,
+            image,
+            format,
+            &options,
+        );
+        self.uploaded.insert(key, uploaded.clone());
+        return Some(uploaded);
+    }
+}
+
+fn collect_instances(
+    node: &gltf::Node,
+    parent_transform: glam::Mat4,
+    mesh_instances: &mut [Vec<Instance>],
+) {
+    let transform = parent_transform * glam::Mat4::from_cols_array_2d(&node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        mesh_instances[mesh.index()].push(Instance {
+            transform: transform.to_cols_array_2d(),
+        });
+    }
+    for child in node.children() {
+        collect_instances(&child, transform, mesh_instances);
+    }
+}
+
+fn build_model(
+    mesh: &gltf::Mesh,
+    buffers: &[gltf::buffer::Data],
+    cache: &mut TextureCache,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layouts: &Layouts,
+    options: &ImportOptions,
+) -> Model {
+    let mut meshes = Vec::new();
+    let mut materials = Vec::new();
+    // glTF material index (None for the default material) to index into `materials`
+    let mut material_ids: Vec<(Option<usize>, usize)> = Vec::new();
+    for primitive in mesh.primitives() {
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            log::warn!(
+                "mesh {}: skipping primitive with unsupported mode {:?}",
+                mesh.index(),
+                primitive.mode()
+            );
+            continue;
+        }
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let Some(positions) = reader.read_positions() else {
+            log::warn!(
+                "mesh {}: skipping primitive without positions",
+                mesh.index()
+            );
+            continue;
+        };
+        let mut vertices = positions
+            .map(|pos| Vertex {
+                pos,
+                tex_cords: [0.0, 0.0],
+                normals: [0.0, 0.0, 0.0],
+                tangent: [0.0, 0.0, 0.0, 0.0],
+            })
+            .collect::<Vec<_>>();
+        let tex_cords = reader.read_tex_coords(0);
+        let has_tex_cords = tex_cords.is_some();
+        if let Some(tex_cords) = tex_cords {
+            for (vertex, tex_cords) in vertices.iter_mut().zip(tex_cords.into_f32()) {
+                vertex.tex_cords = tex_cords;
+            }
+        }
+        let mut indices = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect::<Vec<_>>(),
+            None => (0..vertices.len() as u32).collect(),
+        };
+        match reader.read_normals() {
+            Some(normals) => {
+                for (vertex, normals) in vertices.iter_mut().zip(normals) {
+                    vertex.normals = normals;
+                }
+            }
+            None => match options.normals {
+                GeneratedNormals::Smooth => generate_smooth_normals(&mut vertices, &indices),
+                GeneratedNormals::Flat => {
+                    (vertices, indices) = generate_flat_normals(&vertices, &indices)
+                }
+            },
+        }
+
+        // tangents only match the vertices when the normals were not regenerated flat
+        match reader.read_tangents() {
+            Some(tangents) if vertices.len() == tangents.len() => {
+                for (vertex, tangent) in vertices.iter_mut().zip(tangents) {
+                    vertex.tangent = tangent;
+                }
+            }
+            _ if has_tex_cords => generate_tangents(&mut vertices, &indices),
+            _ => {}
+        }
+
+        let gltf_material = primitive.material();
+        let material_id = match material_ids
+            .iter()
+            .find(|(index, _)| *index == gltf_material.index())
+        {
+            Some((_, material_id)) => *material_id,
+            None => {
+                materials.push(build_material(
+                    &gltf_material,
+                    cache,
+                    device,
+                    queue,
+                    layouts,
+                ));
+                material_ids.push((gltf_material.index(), materials.len() - 1));
+                materials.len() - 1
+            }
+        };
+
+        let (vertex_buffer, index_buffer) = Vertex::make_buffers(device, &vertices, &indices);
+        meshes.push(Mesh {
+            vertex_buffer,
+            index_buffer,
+            len_indicies: indices.len(),
+            material_id,
+        });
+    }
+    return Model { meshes, materials };
+}
+
+/// Maps a metallic-roughness material with its textures onto a PBR `Material`.
+fn build_material(
+    material: &gltf::Material,
+    cache: &mut TextureCache,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layouts: &Layouts,
+) -> Material {
+    let mut load = |texture: gltf::Texture, format| {
+        return cache.load(device, queue, texture, format);
+    };
+    let pbr = material.pbr_metallic_roughness();
+    let base_color = pbr
+        .base_color_texture()
+        .and_then(|info| load(info.texture(), wgpu::TextureFormat::Rgba8UnormSrgb))
+        .unwrap_or_else(|| {
+            Texture::create_solid_color_texture(device, queue, [255, 255, 255, 255])
+        });
+    let mut textures = MaterialTextures::new(device, queue, base_color);
+    // one texture holds roughness in green and metalness in blue
+    if let Some(texture) = pbr
+        .metallic_roughness_texture()
+        .and_then(|info| load(info.texture(), wgpu::TextureFormat::Rgba8Unorm))
+    {
+        textures.metallic = texture.clone();
+        textures.roughness = texture;
+    }
+    if let Some(texture) = material
+        .emissive_texture()
+        .and_then(|info| load(info.texture(), wgpu::TextureFormat::Rgba8UnormSrgb))
+    {
+        textures.emissive = texture;
+    }
+    let occlusion = material.occlusion_texture();
+    if let Some(texture) = occlusion
+        .as_ref()
+        .and_then(|info| load(info.texture(), wgpu::TextureFormat::Rgba8Unorm))
+    {
+        textures.occlusion = texture;
+    }
+    let normal = material.normal_texture();
+    if let Some(texture) = normal
+        .as_ref()
+        .and_then(|info| load(info.texture(), wgpu::TextureFormat::Rgba8Unorm))
+    {
+        textures.normal = texture;
+    }
+    let params = MaterialParams {
+        base_color: pbr.base_color_factor(),
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        emissive: material.emissive_factor(),
+        occlusion_strength: occlusion.map_or(1.0, |info| info.strength()),
+        normal_scale: normal.map_or(1.0, |info| info.scale()),
+        alpha_cutoff: match material.alpha_mode() {
+            gltf::material::AlphaMode::Mask => {
+                material.alpha_cutoff().unwrap_or(DEFAULT_ALPHA_CUTOFF)
+            }
+            _ => 0.0,
+        },
+        ..Default::default()
+    };
+    let mut built = Material::with_textures(device, layouts, Shading::Pbr, params, textures);
+    built.alpha_mode = match material.alpha_mode() {
+        gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+        gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+        gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+    };
+    return built;
+}
+
+/// Maps a glTF sampler onto `SamplerOptions`, mipmaps are only generated for minification
+/// filters that use them.
+fn sampler_options(sampler: &gltf::texture::Sampler) -> SamplerOptions {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+    let address_mode = |mode| match mode {
+        WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+    };
+    let defaults = SamplerOptions::default();
+    let (min_filter, mipmap_filter) = match sampler.min_filter() {
+        None => (defaults.min_filter, Some(defaults.mipmap_filter)),
+        Some(MinFilter::Nearest) => (wgpu::FilterMode::Nearest, None),
+        Some(MinFilter::Linear) => (wgpu::FilterMode::Linear, None),
+        Some(MinFilter::NearestMipmapNearest) => {
+            (wgpu::FilterMode::Nearest, Some(wgpu::FilterMode::Nearest))
+        }
+        Some(MinFilter::LinearMipmapNearest) => {
+            (wgpu::FilterMode::Linear, Some(wgpu::FilterMode::Nearest))
+        }
+        Some(MinFilter::NearestMipmapLinear) => {
+            (wgpu::FilterMode::Nearest, Some(wgpu::FilterMode::Linear))
+        }
+        Some(MinFilter::LinearMipmapLinear) => {
+            (wgpu::FilterMode::Linear, Some(wgpu::FilterMode::Linear))
+        }
+    };
+    return SamplerOptions {
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        mag_filter: match sampler.mag_filter() {
+            None => defaults.mag_filter,
+            Some(MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+            Some(MagFilter::Linear) => wgpu::FilterMode::Linear,
+        },
+        min_filter,
+        mipmap_filter: mipmap_filter.unwrap_or(wgpu::FilterMode::Nearest),
+        mipmaps: mipmap_filter.is_some(),
+        ..defaults
+    };
+}
+
+/// Converts a decoded glTF image to 8 bit RGBA, `None` for formats we cannot show.
+fn to_rgba(data: &gltf::image::Data) -> Option<image::RgbaImage> {
+    use gltf::image::Format;
+
+    let (width, height) = (data.width, data.height);
+    let pixels = data.pixels.clone();
+    let wide = || bytemuck::pod_collect_to_vec::<u8, u16>(&data.pixels);
+    let float = || bytemuck::pod_collect_to_vec::<u8, f32>(&data.pixels);
+    let image: Option<image::DynamicImage> = match data.format {
+        Format::R8 => image::GrayImage::from_raw(width, height, pixels).map(Into::into),
+        Format::R8G8 => image::GrayAlphaImage::from_raw(width, height, pixels).map(Into::into),
+        Format::R8G8B8 => image::RgbImage::from_raw(width, height, pixels).map(Into::into),
+        Format::R8G8B8A8 => image::RgbaImage::from_raw(width, height, pixels).map(Into::into),
+        Format::R16 => image::ImageBuffer::<image::Luma<u16>, _>::from_raw(width, height, wide())
+            .map(Into::into),
+        Format::R16G16 => {
+            image::ImageBuffer::<image::LumaA<u16>, _>::from_raw(width, height, wide())
+                .map(Into::into)
+        }
+        Format::R16G16B16 => {
+            image::ImageBuffer::<image::Rgb<u16>, _>::from_raw(width, height, wide())
+                .map(Into::into)
+        }
+        Format::R16G16B16A16 => {
+            image::ImageBuffer::<image::Rgba<u16>, _>::from_raw(width, height, wide())
+                .map(Into::into)
+        }
+        Format::R32G32B32FLOAT => {
+            image::Rgb32FImage::from_raw(width, height, float()).map(Into::into)
+        }
+        Format::R32G32B32A32FLOAT => {
+            image::Rgba32FImage::from_raw(width, height, float()).map(Into::into)
+        }
+    };
+    if image.is_none() {
+        log::warn!("skipping {:?} image with mismatched size", data.format);
+    }
+    return image.map(|image| image.to_rgba8());
+}
@@ -1,9 +1,7 @@
 use std::mem;
-use wgpu::util::BufferInitDescriptor;
-use wgpu::util::DeviceExt;
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Instance {
     pub transform: [[f32; 4]; 4],
 }
@@ -15,10 +13,17 @@ impl Instance {
         scale: glam::f32::Vec3,
     ) -> Instance {
         let transform =
-            glam::f32::Mat4::from_scale_rotation_translation(scale,rotation, translation).to_cols_array_2d();
+            glam::f32::Mat4::from_scale_rotation_translation(scale, rotation, translation)
+                .to_cols_array_2d();
         return Instance { transform };
     }
 
+    /// Translation of the instance, where its model's origin ends up.
+    pub fn position(&self) -> glam::f32::Vec3 {
+        let [x, y, z, _] = self.transform[3];
+        return glam::f32::Vec3::new(x, y, z);
+    }
+
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         return wgpu::VertexBufferLayout {
             array_stride: mem::size_of::<Instance>() as wgpu::BufferAddress,
@@ -47,14 +52,62 @@ impl Instance {
             ],
         };
     }
+}
+
+/// Instance buffer kept alive across frames, reuploaded only when the instances change.
+pub struct InstanceBuffer {
+    pub buffer: wgpu::Buffer,
+    /// Instances currently in `buffer`.
+    pub instances: Vec<Instance>,
+}
 
-    pub fn make_buffer(device: &wgpu::Device, instances: &[Instance]) -> wgpu::Buffer {
-        let instance_buffer = device.create_buffer_init(&BufferInitDescriptor {
+impl InstanceBuffer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &[Instance],
+    ) -> InstanceBuffer {
+        let buffer = InstanceBuffer::create_buffer(device, instances.len());
+        queue.write_buffer(&buffer, 0, bytemuck::cast_slice(instances));
+        return InstanceBuffer {
+            buffer,
+            instances: instances.to_vec(),
+        };
+    }
+
+    fn create_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        return device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
-            contents: bytemuck::cast_slice(instances),
-            usage: wgpu::BufferUsages::VERTEX,
+            size: (capacity.max(1) * mem::size_of::<Instance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
         });
-        return instance_buffer;
+    }
+
+    /// Writes `instances` to the buffer if they differ from the last upload, the buffer
+    /// is only recreated when it has to grow.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, instances: &[Instance]) {
+        if self.instances == instances {
+            return;
+        }
+        let size = mem::size_of_val(instances) as wgpu::BufferAddress;
+        if size > self.buffer.size() {
+            self.buffer = InstanceBuffer::create_buffer(device, instances.len());
+        }
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(instances));
+        self.instances.clear();
+        self.instances.extend_from_slice(instances);
+    }
+}
+
+impl std::ops::Mul<Instance> for Instance {
+    type Output = Instance;
+
+    fn mul(self, rhs: Instance) -> Self::Output {
+        let transform = (glam::f32::Mat4::from_cols_array_2d(&self.transform)
+            * glam::f32::Mat4::from_cols_array_2d(&rhs.transform))
+        .to_cols_array_2d();
+        return Instance { transform };
     }
 }
 
@@ -0,0 +1,44 @@
+//! Small wgpu renderer with Blinn-Phong or metallic-roughness PBR shading, shadow mapped point
+//! lights, a directional light with cascaded shadow maps, screen-space ambient occlusion, bloom
+//! and HDR tonemapping.
+//!
+//! `State` is the renderer, it draws a `Scene` either into a winit window or offscreen.
+//! Wavefront models are loaded with `Model::load_model`, glTF files with `gltf_loader::load_gltf`,
+//! and placed through `Instance` transforms. The view is controlled through `State::camera`.
+
+pub mod binding;
+pub mod bloom;
+pub mod camera;
+pub mod error;
+pub mod exposure;
+pub mod gltf_loader;
+pub mod instance;
+pub mod layouts;
+pub mod light;
+pub mod mipmap;
+pub mod model;
+pub mod offscreen;
+pub mod scene;
+pub mod scene_file;
+pub mod shadow;
+pub mod ssao;
+pub mod state;
+pub mod texture;
+pub mod tonemap;
+pub mod vertex;
+
+pub use bloom::BloomConfig;
+pub use camera::{Camera, CameraMovement};
+pub use error::{Error, Result};
+pub use instance::Instance;
+pub use light::{DirectionalLight, Light, ShadowSettings, SpotLight};
+pub use model::{
+    create_plane, AlphaMode, GeneratedNormals, ImportOptions, Material, MaterialTextures, Mesh,
+    Model, Shading,
+};
+pub use scene::Scene;
+pub use scene_file::SceneFile;
+pub use shadow::{CascadeConfig, ShadowConfig, ShadowFilter};
+pub use ssao::{SsaoConfig, SsaoQuality};
+pub use state::{RenderConfig, State, Target};
+pub use tonemap::{ToneMapConfig, ToneMapper};
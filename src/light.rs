@@ -1,15 +1,62 @@
 use crate::binding::Bindeable;
 
+/// Most point lights shaded at once, lights past this in `Scene::lights` are ignored.
+pub const MAX_LIGHTS: usize = 16;
+
+/// Shadow map projection and biases of a light, all distances in world units.
+/// Too little bias shows as acne, stripes of self shadowing on lit surfaces, too much detaches
+/// shadows from their casters (peter-panning).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShadowSettings {
+    /// Near plane of the shadow map projection, unused by directional lights.
+    pub near: f32,
+    /// Constant distance receivers are moved towards the light before the depth comparison.
+    pub depth_bias: f32,
+    /// Extra depth bias growing with the angle between surface and light.
+    pub slope_bias: f32,
+    /// Distance receivers are moved along their normal before the shadow map lookup.
+    pub normal_bias: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        return ShadowSettings {
+            near: 0.1,
+            depth_bias: 0.02,
+            slope_bias: 0.02,
+            normal_bias: 0.02,
+        };
+    }
+}
+
+/// A point light casting shadows through its own cube map.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Light {
     pub pos: [f32; 3],
-    pub _padding: u32,
+    /// Distance at which the light has faded out completely, also the far plane of its shadows.
+    pub range: f32,
     pub color: [f32; 3],
-    pub _padding2: u32,
-    pub view_proj: [[f32;4];4]
+    pub intensity: f32,
+    pub shadow: ShadowSettings,
+    /// Set per cube face by the shadow pass.
+    pub view_proj: [[f32; 4]; 4],
 }
 
+impl Light {
+    pub fn new(pos: [f32; 3], color: [f32; 3]) -> Light {
+        return Light {
+            pos,
+            range: 100.0,
+            color,
+            intensity: 1.0,
+            shadow: ShadowSettings::default(),
+            view_proj: [[0.0; 4]; 4],
+        };
+    }
+}
 
 impl Bindeable for Light {
     fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
@@ -21,7 +68,7 @@ impl Bindeable for Light {
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
-                    min_binding_size: wgpu::BufferSize::new(96),
+                    min_binding_size: wgpu::BufferSize::new(112),
                 },
                 count: None,
             }],
@@ -29,5 +76,132 @@ impl Bindeable for Light {
     }
 }
 
+/// Uniform layout of the light list in `Globals`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightsRaw {
+    pub count: u32,
+    pub _padding: [u32; 3],
+    pub lights: [Light; MAX_LIGHTS],
+}
+
+impl LightsRaw {
+    pub fn new(lights: &[Light]) -> LightsRaw {
+        let mut raw = LightsRaw {
+            count: lights.len().min(MAX_LIGHTS) as u32,
+            _padding: [0; 3],
+            lights: [Light::new([0.0; 3], [0.0; 3]); MAX_LIGHTS],
+        };
+        for (slot, light) in raw.lights.iter_mut().zip(lights.iter()) {
+            *slot = *light;
+        }
+        return raw;
+    }
+}
+
+/// Most shadow cascades a directional light can be split into.
+pub const MAX_CASCADES: usize = 4;
+
+/// A light infinitely far away, like the sun, shining along `direction`.
+#[derive(Copy, Clone, Debug)]
+pub struct DirectionalLight {
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub shadow: ShadowSettings,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: [f32; 3], color: [f32; 3]) -> DirectionalLight {
+        return DirectionalLight {
+            direction,
+            color,
+            intensity: 1.0,
+            shadow: ShadowSettings::default(),
+        };
+    }
+}
+
+/// Uniform layout of the directional light and its cascades in `Globals`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SunRaw {
+    pub direction: [f32; 3],
+    /// 0 when the scene has no directional light.
+    pub enabled: u32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub cascade_count: u32,
+    /// Fraction of every cascade, at its far end, blended into the next one.
+    pub blend: f32,
+    /// Non zero tints every cascade in its own colour.
+    pub debug: u32,
+    pub _padding: u32,
+    pub shadow: ShadowSettings,
+    /// Far end of every cascade, as a distance along the camera direction.
+    pub splits: [f32; MAX_CASCADES],
+    pub view_projs: [[[f32; 4]; 4]; MAX_CASCADES],
+}
+
+/// Most spot lights shaded at once, lights past this in `Scene::spot_lights` are ignored.
+pub const MAX_SPOT_LIGHTS: usize = 8;
+
+/// A light shining a cone along `direction`, with a single perspective shadow map.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpotLight {
+    pub pos: [f32; 3],
+    /// Distance at which the light has faded out completely, also the far plane of its shadows.
+    pub range: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub direction: [f32; 3],
+    /// Angle from `direction` in radians up to which the light shines at full strength.
+    pub inner_angle: f32,
+    pub shadow: ShadowSettings,
+    /// Angle from `direction` in radians past which the light is off.
+    pub outer_angle: f32,
+    pub _padding: [f32; 3],
+    /// Set by the shadow pass.
+    pub view_proj: [[f32; 4]; 4],
+}
+
+impl SpotLight {
+    pub fn new(pos: [f32; 3], direction: [f32; 3], color: [f32; 3]) -> SpotLight {
+        return SpotLight {
+            pos,
+            range: 100.0,
+            color,
+            intensity: 1.0,
+            direction,
+            inner_angle: f32::to_radians(20.0),
+            shadow: ShadowSettings::default(),
+            outer_angle: f32::to_radians(30.0),
+            _padding: [0.0; 3],
+            view_proj: [[0.0; 4]; 4],
+        };
+    }
+}
 
+/// Uniform layout of the spot light list in `Globals`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpotLightsRaw {
+    pub count: u32,
+    pub _padding: [u32; 3],
+    pub lights: [SpotLight; MAX_SPOT_LIGHTS],
+}
 
+impl SpotLightsRaw {
+    pub fn new(lights: &[SpotLight]) -> SpotLightsRaw {
+        let mut raw = SpotLightsRaw {
+            count: lights.len().min(MAX_SPOT_LIGHTS) as u32,
+            _padding: [0; 3],
+            lights: [SpotLight::new([0.0; 3], [0.0, -1.0, 0.0], [0.0; 3]); MAX_SPOT_LIGHTS],
+        };
+        for (slot, light) in raw.lights.iter_mut().zip(lights.iter()) {
+            *slot = *light;
+        }
+        return raw;
+    }
+}
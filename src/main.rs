@@ -1,25 +1,276 @@
-mod camera;
-mod instance;
-mod layouts;
-mod light;
-mod model;
-mod state;
-mod texture;
-mod binding;
-mod vertex;
-
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
 
-use state::State;
+use clap::{Parser, ValueEnum};
+use spinny::{
+    BloomConfig, CameraMovement, CascadeConfig, Light, RenderConfig, SceneFile, ShadowConfig,
+    ShadowFilter, SsaoConfig, SsaoQuality, State, Target, ToneMapConfig, ToneMapper,
+};
 use winit::application::ApplicationHandler;
+use winit::dpi::PhysicalSize;
 use winit::event::{ElementState, KeyEvent};
 use winit::event_loop::EventLoop;
 use winit::keyboard::Key;
-use winit::window::{self, WindowAttributes};
+use winit::window::{self, Fullscreen, WindowAttributes};
+
+/// Spinning teapots, Blinn-Phong shading and point light shadows.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Scene file (.ron) or a single model (.obj, .gltf or .glb) to show
+    #[arg(default_value = "./assets/scene.ron")]
+    path: PathBuf,
+    /// Window or image width in pixels
+    #[arg(long, default_value_t = 800)]
+    width: u32,
+    /// Window or image height in pixels
+    #[arg(long, default_value_t = 600)]
+    height: u32,
+    /// Open a borderless fullscreen window
+    #[arg(long)]
+    fullscreen: bool,
+    /// Present mode of the window surface
+    #[arg(long, value_enum, default_value_t = Vsync::Auto)]
+    vsync: Vsync,
+    /// Width and height of each shadow cube map face and sun shadow cascade
+    #[arg(long, default_value_t = 1024)]
+    shadow_map_size: u32,
+    /// How point light shadow edges are filtered
+    #[arg(long, value_enum, default_value_t = Filter::Hard)]
+    shadow_filter: Filter,
+    /// Radius of the lights in world units, sets how soft PCSS shadows get
+    #[arg(long, default_value_t = 0.1)]
+    light_size: f32,
+    /// Number of shadow cascades of the sun, 1 to 4
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..=4))]
+    cascades: u32,
+    /// Cascade split distribution, 0 for even splits and 1 for logarithmic ones
+    #[arg(long, default_value_t = 0.5)]
+    cascade_split: f32,
+    /// Tint each sun shadow cascade in its own colour
+    #[arg(long)]
+    debug_cascades: bool,
+    /// Curve mapping the HDR scene to the display
+    #[arg(long, value_enum, default_value_t = Tonemap::Linear)]
+    tonemap: Tonemap,
+    /// Exposure multiplier, a compensation on top of the measured one with --auto-exposure
+    #[arg(long, default_value_t = 1.0)]
+    exposure: f32,
+    /// Expose for the average scene luminance, measured every frame
+    #[arg(long)]
+    auto_exposure: bool,
+    /// Add a glow around surfaces brighter than the bloom threshold
+    #[arg(long)]
+    bloom: bool,
+    /// Brightness above which surfaces bloom
+    #[arg(long, default_value_t = 1.0)]
+    bloom_threshold: f32,
+    /// Strength of the bloom glow
+    #[arg(long, default_value_t = 0.1)]
+    bloom_intensity: f32,
+    /// Spread of the bloom glow
+    #[arg(long, default_value_t = 1.0)]
+    bloom_radius: f32,
+    /// Darken ambient light in creases and contact areas with screen-space ambient occlusion
+    #[arg(long)]
+    ssao: bool,
+    /// Number of ambient occlusion samples per pixel
+    #[arg(long, value_enum, default_value_t = Quality::Medium)]
+    ssao_quality: Quality,
+    /// Distance searched for occluders, in world units
+    #[arg(long, default_value_t = 0.5)]
+    ssao_radius: f32,
+    /// Show the raw ambient occlusion buffer instead of the shaded scene, enables --ssao
+    #[arg(long)]
+    debug_ssao: bool,
+    /// Graphics API to render with
+    #[arg(long, value_enum, default_value_t = Backend::All)]
+    backend: Backend,
+    /// Use a software adapter instead of a GPU
+    #[arg(long)]
+    software: bool,
+    /// Render offscreen to png files instead of opening a window
+    #[arg(long)]
+    headless: bool,
+    /// Number of frames to render in headless mode
+    #[arg(long, default_value_t = 1, requires = "headless")]
+    frames: u32,
+    /// Directory headless frames are written to
+    #[arg(long, default_value = ".", requires = "headless")]
+    out: PathBuf,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Vsync {
+    /// Fifo where available, tear free
+    Auto,
+    /// Immediate or mailbox where available, lowest latency
+    Off,
+    Fifo,
+    FifoRelaxed,
+    Immediate,
+    Mailbox,
+}
+
+impl Vsync {
+    fn present_mode(self) -> wgpu::PresentMode {
+        return match self {
+            Vsync::Auto => wgpu::PresentMode::AutoVsync,
+            Vsync::Off => wgpu::PresentMode::AutoNoVsync,
+            Vsync::Fifo => wgpu::PresentMode::Fifo,
+            Vsync::FifoRelaxed => wgpu::PresentMode::FifoRelaxed,
+            Vsync::Immediate => wgpu::PresentMode::Immediate,
+            Vsync::Mailbox => wgpu::PresentMode::Mailbox,
+        };
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Filter {
+    /// Single tap, hard edges
+    Hard,
+    /// Fixed size Poisson disk
+    Pcf,
+    /// Penumbra grows with the distance to the occluder
+    Pcss,
+}
+
+impl Filter {
+    fn shadow_filter(self) -> ShadowFilter {
+        return match self {
+            Filter::Hard => ShadowFilter::Hard,
+            Filter::Pcf => ShadowFilter::Pcf,
+            Filter::Pcss => ShadowFilter::Pcss,
+        };
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Tonemap {
+    /// Clip at 1
+    Linear,
+    Reinhard,
+    /// ACES filmic curve
+    Aces,
+    Agx,
+}
+
+impl Tonemap {
+    fn tone_mapper(self) -> ToneMapper {
+        return match self {
+            Tonemap::Linear => ToneMapper::Linear,
+            Tonemap::Reinhard => ToneMapper::Reinhard,
+            Tonemap::Aces => ToneMapper::Aces,
+            Tonemap::Agx => ToneMapper::Agx,
+        };
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Quality {
+    /// 8 samples
+    Low,
+    /// 16 samples
+    Medium,
+    /// 32 samples
+    High,
+}
+
+impl Quality {
+    fn ssao_quality(self) -> SsaoQuality {
+        return match self {
+            Quality::Low => SsaoQuality::Low,
+            Quality::Medium => SsaoQuality::Medium,
+            Quality::High => SsaoQuality::High,
+        };
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Backend {
+    All,
+    Vulkan,
+    Metal,
+    Dx12,
+    Gl,
+}
+
+impl Backend {
+    fn backends(self) -> wgpu::Backends {
+        return match self {
+            Backend::All => wgpu::Backends::all(),
+            Backend::Vulkan => wgpu::Backends::VULKAN,
+            Backend::Metal => wgpu::Backends::METAL,
+            Backend::Dx12 => wgpu::Backends::DX12,
+            Backend::Gl => wgpu::Backends::GL,
+        };
+    }
+}
+
+impl Args {
+    fn render_config(&self) -> RenderConfig {
+        return RenderConfig {
+            backends: self.backend.backends(),
+            force_fallback_adapter: self.software,
+            present_mode: self.vsync.present_mode(),
+            shadow_map_size: self.shadow_map_size,
+            cascades: CascadeConfig {
+                count: self.cascades,
+                split_lambda: self.cascade_split,
+                debug: self.debug_cascades,
+                ..Default::default()
+            },
+            shadows: ShadowConfig {
+                filter: self.shadow_filter.shadow_filter(),
+                light_size: self.light_size,
+                ..Default::default()
+            },
+            tonemapping: ToneMapConfig {
+                operator: self.tonemap.tone_mapper(),
+                exposure: self.exposure,
+                auto_exposure: self.auto_exposure,
+                ..Default::default()
+            },
+            bloom: BloomConfig {
+                enabled: self.bloom,
+                threshold: self.bloom_threshold,
+                intensity: self.bloom_intensity,
+                radius: self.bloom_radius,
+            },
+            ssao: SsaoConfig {
+                enabled: self.ssao || self.debug_ssao,
+                quality: self.ssao_quality.ssao_quality(),
+                radius: self.ssao_radius,
+                debug: self.debug_ssao,
+                ..Default::default()
+            },
+        };
+    }
+
+    fn load_scene(&self, state: &mut State) {
+        let is_scene = self
+            .path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("ron"));
+        let result = match is_scene {
+            true => state.load_scene(&self.path),
+            false => state.set_scene(&SceneFile::for_model(&self.path)),
+        };
+        or_exit(result);
+    }
+}
+
+/// Prints the error and exits, the viewer has nothing sensible to fall back to.
+fn or_exit<T, E: std::fmt::Display>(result: Result<T, E>) -> T {
+    return result.unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        std::process::exit(1);
+    });
+}
 
-#[derive(Default)]
 struct App {
+    args: Args,
     state: Option<State>,
     last_updated: Option<std::time::Instant>,
     delta_time: Option<f32>,
@@ -27,42 +278,93 @@ struct App {
 }
 
 impl App {
+    fn new(args: Args) -> App {
+        return App {
+            args,
+            state: None,
+            last_updated: None,
+            delta_time: None,
+            focused: None,
+        };
+    }
+
     fn input_handler(&mut self, key: Key, delta_time: f32) {
         let state = self.state.as_mut().unwrap();
-        match key.as_ref() {
-            Key::Character("w") => {
-                state.camera.position += state.camera.direction * state.camera.speed * delta_time;
+        let movement = match key.as_ref() {
+            Key::Character("w") => CameraMovement::Forward,
+            Key::Character("a") => CameraMovement::Left,
+            Key::Character("s") => CameraMovement::Backward,
+            Key::Character("d") => CameraMovement::Right,
+            // place a light where the camera is, or take the last one away
+            Key::Character("l") => {
+                let light = Light::new(state.camera.position.into(), [1.0, 1.0, 1.0]);
+                state.scene.add_light(light);
+                return;
             }
-            Key::Character("a") => {
-                state.camera.position +=
-                    glam::Vec3::Y.cross(state.camera.direction) * state.camera.speed * delta_time;
+            Key::Character("k") => {
+                state.scene.lights.pop();
+                return;
             }
-            Key::Character("s") => {
-                state.camera.position -= state.camera.direction * state.camera.speed * delta_time;
+            Key::Character("f") => {
+                state.config.shadows.filter = state.config.shadows.filter.next();
+                return;
             }
-
-            Key::Character("d") => {
-                state.camera.position -=
-                    glam::Vec3::Y.cross(state.camera.direction) * state.camera.speed * delta_time;
+            Key::Character("c") => {
+                state.config.cascades.debug = !state.config.cascades.debug;
+                return;
             }
-            _ => (),
-        }
+            Key::Character("t") => {
+                let tonemapping = &mut state.config.tonemapping;
+                tonemapping.operator = tonemapping.operator.next();
+                return;
+            }
+            Key::Character("e") => {
+                let tonemapping = &mut state.config.tonemapping;
+                tonemapping.auto_exposure = !tonemapping.auto_exposure;
+                return;
+            }
+            Key::Character("b") => {
+                state.config.bloom.enabled = !state.config.bloom.enabled;
+                return;
+            }
+            Key::Character("o") => {
+                state.config.ssao.enabled = !state.config.ssao.enabled;
+                return;
+            }
+            Key::Character("i") => {
+                state.config.ssao.quality = state.config.ssao.quality.next();
+                return;
+            }
+            Key::Character("v") => {
+                state.config.ssao.debug = !state.config.ssao.debug;
+                // the debug view needs the occlusion computed
+                state.config.ssao.enabled |= state.config.ssao.debug;
+                return;
+            }
+            _ => return,
+        };
+        state.camera.move_by(movement, delta_time);
     }
 
     fn mouse_hanlder(&mut self, delta_mouse: (f64, f64), delta_time: f32) {
         let state = self.state.as_mut().unwrap();
-        state.camera.yaw += delta_mouse.0 as f32 * state.camera.speed * delta_time * 0.05;
-        state.camera.pitch -= delta_mouse.1 as f32 * state.camera.speed * delta_time * 0.05;
+        state.camera.look_by(delta_mouse, delta_time);
     }
 }
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
-        let window = Arc::new(
-            event_loop
-                .create_window(WindowAttributes::default())
-                .unwrap(),
-        );
-        let state = pollster::block_on(State::new(window.clone()));
+        let mut attributes = WindowAttributes::default()
+            .with_title("spinny")
+            .with_inner_size(PhysicalSize::new(self.args.width, self.args.height));
+        if self.args.fullscreen {
+            attributes = attributes.with_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+        let window = Arc::new(or_exit(event_loop.create_window(attributes)));
+        let mut state = or_exit(pollster::block_on(State::new(
+            window.clone(),
+            self.args.render_config(),
+        )));
+        self.args.load_scene(&mut state);
         self.state = Some(state);
         self.last_updated = Some(Instant::now());
         self.delta_time = Some(0.0);
@@ -95,10 +397,15 @@ impl ApplicationHandler for App {
                 event_loop.exit();
             }
             winit::event::WindowEvent::RedrawRequested => {
-                state.spin_teapots();
+                state
+                    .scene
+                    .rotate_instances(0, glam::Quat::from_rotation_y(1.0f32.to_radians()));
                 state.camera.update_view_matrix();
-                state.render();
-                state.window.request_redraw();
+                if let Err(err) = state.render() {
+                    eprintln!("error: {err}");
+                    event_loop.exit();
+                }
+                state.request_redraw();
             }
             winit::event::WindowEvent::KeyboardInput {
                 event:
@@ -109,6 +416,17 @@ impl ApplicationHandler for App {
                     },
                 ..
             } => self.input_handler(logical_key, self.delta_time.unwrap()),
+            winit::event::WindowEvent::Resized(new_size) => {
+                state.resize(new_size);
+            }
+            // winit follows up with a Resized event carrying the new physical size, until
+            // then keep the depth texture and projection in sync with the current one
+            winit::event::WindowEvent::ScaleFactorChanged { .. } => {
+                if let Target::Window { window, .. } = &state.target {
+                    let new_size = window.inner_size();
+                    state.resize(new_size);
+                }
+            }
             winit::event::WindowEvent::Focused(focused) => {
                 self.focused = Some(focused);
             }
@@ -122,20 +440,45 @@ impl ApplicationHandler for App {
         _device_id: winit::event::DeviceId,
         event: winit::event::DeviceEvent,
     ) {
-        match event {
-            winit::event::DeviceEvent::MouseMotion { delta } => {
-                if self.focused.unwrap(){
-                    self.mouse_hanlder(delta, self.delta_time.unwrap())
-                }
+        if let winit::event::DeviceEvent::MouseMotion { delta } = event {
+            if self.focused.unwrap() {
+                self.mouse_hanlder(delta, self.delta_time.unwrap())
             }
-            _ => (),
         }
     }
 }
 
+/// Renders `args.frames` frames offscreen and writes them to `args.out` as numbered pngs.
+fn run_headless(args: &Args) {
+    let mut state = or_exit(pollster::block_on(State::new_headless(
+        PhysicalSize::new(args.width, args.height),
+        args.render_config(),
+    )));
+    args.load_scene(&mut state);
+    or_exit(std::fs::create_dir_all(&args.out));
+    for frame in 0..args.frames {
+        if frame > 0 {
+            state
+                .scene
+                .rotate_instances(0, glam::Quat::from_rotation_y(1.0f32.to_radians()));
+        }
+        state.camera.update_view_matrix();
+        let path = args.out.join(format!("frame_{frame:04}.png"));
+        or_exit(or_exit(state.render_to_image()).save(&path));
+        println!("{}", path.display());
+    }
+}
+
 fn main() {
-    let event_loop = EventLoop::new().unwrap();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("spinny=warn"))
+        .init();
+    let args = Args::parse();
+    if args.headless {
+        run_headless(&args);
+        return;
+    }
+    let event_loop = or_exit(EventLoop::new());
     event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
-    let mut app = App::default();
+    let mut app = App::new(args);
     let _ = event_loop.run_app(&mut app);
 }
@@ -1,14 +1,22 @@
 use std::default;
+use std::path::Path;
 
-use crate::binding::Bindeable;
-use crate::texture::{self, Texture};
-use crate::vertex::{Vertex, PLANE_INDICIES, PLANE_VERTICIES};
+use crate::binding::{Bindeable, Binding, TextureBinding};
+use crate::error::{Error, Result};
+use crate::layouts::Layouts;
+use crate::mipmap::MipmapGenerator;
+use crate::texture::{self, SamplerOptions, Texture};
+use crate::vertex::{
+    generate_flat_normals, generate_smooth_normals, generate_tangents, Vertex, PLANE_INDICIES,
+    PLANE_VERTICIES,
+};
 
 pub struct Mesh {
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub len_indicies: usize,
-    pub material_id: Option<usize>,
+    /// Index into `Model::materials`, always valid after loading.
+    pub material_id: usize,
 }
 
 pub struct Model {
@@ -16,8 +24,58 @@ pub struct Model {
     pub materials: Vec<Material>,
 }
 
+/// How normals are generated for meshes that come without them.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+pub enum GeneratedNormals {
+    /// Shared vertices average the normals of their faces.
+    #[default]
+    Smooth,
+    /// Every face gets its own vertices, giving a faceted look.
+    Flat,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ImportOptions {
+    pub normals: GeneratedNormals,
+}
+
 impl Model {
-    pub fn load_model(obj_file_path: &str, device: &wgpu::Device, queue: &wgpu::Queue) -> Model {
+    pub fn load_model(
+        obj_file_path: impl AsRef<Path>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layouts: &Layouts,
+        mipmaps: &MipmapGenerator,
+    ) -> Result<Model> {
+        return Model::load_model_with(
+            obj_file_path,
+            device,
+            queue,
+            layouts,
+            mipmaps,
+            &ImportOptions::default(),
+        );
+    }
+
+    /// Loads a Wavefront OBJ. Missing normals are generated, missing texture coordinates
+    /// default to zero and textures are looked up relative to the OBJ file. When the MTL
+    /// library cannot be loaded every mesh falls back to the default material.
+    pub fn load_model_with(
+        obj_file_path: impl AsRef<Path>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layouts: &Layouts,
+        mipmaps: &MipmapGenerator,
+        options: &ImportOptions,
+    ) -> Result<Model> {
+        let obj_file_path = obj_file_path.as_ref();
+        let base_dir = obj_file_path.parent().unwrap_or(Path::new(""));
+        if !obj_file_path.is_file() {
+            return Err(Error::Io {
+                path: obj_file_path.to_owned(),
+                source: std::io::Error::new(std::io::ErrorKind::NotFound, "no such file"),
+            });
+        }
         let (loaded_models, loaded_materials) = tobj::load_obj(
             obj_file_path,
             &tobj::LoadOptions {
@@ -26,58 +84,414 @@ impl Model {
                 ..Default::default()
             },
         )
-        .unwrap();
+        .map_err(|source| Error::Obj {
+            path: obj_file_path.to_owned(),
+            source,
+        })?;
+        let loaded_materials = loaded_materials.unwrap_or_else(|err| {
+            log::warn!(
+                "{}: failed to load mtl, using the default material: {}",
+                obj_file_path.display(),
+                err
+            );
+            Vec::new()
+        });
+        // meshes without a usable material share one default material appended at the end
+        let default_material_id = loaded_materials.len();
         let mut meshes: Vec<Mesh> = Vec::new();
         for model in loaded_models.iter() {
-            let vertices = (0..model.mesh.positions.len() / 3)
+            let vertex_count = model.mesh.positions.len() / 3;
+            let has_tex_cords = model.mesh.texcoords.len() >= vertex_count * 2;
+            let has_normals = model.mesh.normals.len() >= vertex_count * 3;
+            let mut vertices = (0..vertex_count)
                 .map(|i| Vertex {
                     pos: [
                         model.mesh.positions[i * 3],
                         model.mesh.positions[i * 3 + 1],
                         model.mesh.positions[i * 3 + 2],
                     ],
-                    tex_cords: [
-                        model.mesh.texcoords[i * 2],
-                        1.0 - model.mesh.texcoords[i * 2 + 1],
-                    ],
-                    normals: [
-                        model.mesh.normals[i * 3],
-                        model.mesh.normals[i * 3 + 1],
-                        model.mesh.normals[i * 3 + 2],
-                    ],
+                    tex_cords: match has_tex_cords {
+                        true => [
+                            model.mesh.texcoords[i * 2],
+                            1.0 - model.mesh.texcoords[i * 2 + 1],
+                        ],
+                        false => [0.0, 0.0],
+                    },
+                    normals: match has_normals {
+                        true => [
+                            model.mesh.normals[i * 3],
+                            model.mesh.normals[i * 3 + 1],
+                            model.mesh.normals[i * 3 + 2],
+                        ],
+                        false => [0.0, 0.0, 0.0],
+                    },
+                    tangent: [0.0, 0.0, 0.0, 0.0],
                 })
                 .collect::<Vec<_>>();
-            let (vertex_buffer, index_buffer) =
-                Vertex::make_buffers(device, &vertices, &model.mesh.indices);
+            let mut indices = model.mesh.indices.clone();
+            if !has_normals {
+                match options.normals {
+                    GeneratedNormals::Smooth => generate_smooth_normals(&mut vertices, &indices),
+                    GeneratedNormals::Flat => {
+                        (vertices, indices) = generate_flat_normals(&vertices, &indices)
+                    }
+                }
+            }
+            if has_tex_cords {
+                generate_tangents(&mut vertices, &indices);
+            }
+            let (vertex_buffer, index_buffer) = Vertex::make_buffers(device, &vertices, &indices);
             meshes.push(Mesh {
                 vertex_buffer,
                 index_buffer,
-                material_id: model.mesh.material_id,
-                len_indicies: model.mesh.indices.len(),
+                material_id: model
+                    .mesh
+                    .material_id
+                    .filter(|id| *id < loaded_materials.len())
+                    .unwrap_or(default_material_id),
+                len_indicies: indices.len(),
             });
         }
+        // the mip chains of every texture are generated with a single submit
+        let mut encoder = device.create_command_encoder(&Default::default());
         let mut materials: Vec<Material> = Vec::new();
-        for material in loaded_materials.unwrap().iter() {
-            let texture = match &material.diffuse_texture {
-                Some(texture_file_path) => {
-                    let image_rgba = image::open(&("./assets/".to_owned() + &texture_file_path))
-                        .unwrap()
-                        .to_rgba8();
-                    Texture::load_texture(device, queue, &image_rgba)
-                }
-                None => Texture::create_solid_color_texture(device, queue, [125, 125, 125, 125]),
+        for material in loaded_materials.iter() {
+            materials.push(load_mtl(
+                material,
+                base_dir,
+                device,
+                queue,
+                &mut encoder,
+                layouts,
+                mipmaps,
+            )?);
+        }
+        queue.submit([encoder.finish()]);
+
+        if meshes
+            .iter()
+            .any(|mesh| mesh.material_id == default_material_id)
+        {
+            materials.push(Material::fallback(device, queue, layouts));
+        }
+
+        return Ok(Model { meshes, materials });
+    }
+}
+
+/// MTL statements of the PBR extension, a material using any of them is shaded as PBR.
+const MTL_PBR_KEYS: [&str; 4] = ["Pr", "Pm", "map_Pr", "map_Pm"];
+
+/// Value of an MTL statement tobj does not parse itself, like the PBR extension's.
+fn mtl_param<'a>(material: &'a tobj::Material, key: &str) -> Option<&'a str> {
+    return material
+        .unknown_param
+        .get(key)
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty());
+}
+
+fn mtl_float(material: &tobj::Material, key: &str) -> Option<f32> {
+    return mtl_param(material, key).and_then(|value| value.parse().ok());
+}
+
+fn mtl_color(material: &tobj::Material, key: &str) -> Option<[f32; 3]> {
+    let values = mtl_param(material, key)?
+        .split_whitespace()
+        .map(|value| value.parse::<f32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    return match values[..] {
+        [r, g, b] => Some([r, g, b]),
+        // a single value sets all three channels
+        [value] => Some([value; 3]),
+        _ => None,
+    };
+}
+
+/// Least and most arguments a texture statement option takes, `-o`, `-s` and `-t` default the
+/// coordinates they leave out.
+fn texture_option_arguments(option: &str) -> Option<(usize, usize)> {
+    return match option {
+        "-blendu" | "-blendv" | "-bm" | "-boost" | "-cc" | "-clamp" | "-imfchan" | "-texres"
+        | "-type" => Some((1, 1)),
+        "-mm" => Some((2, 2)),
+        "-o" | "-s" | "-t" => Some((1, 3)),
+        _ => None,
+    };
+}
+
+/// `text` without its first word and the whitespace after it.
+fn skip_word(text: &str) -> &str {
+    return text
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .map_or("", |(_, rest)| rest.trim_start());
+}
+
+/// File name of a texture statement, skipping options such as `-bm 1.0` before it. The name
+/// may contain spaces.
+fn texture_file(statement: &str) -> Option<&str> {
+    let mut rest = statement.trim();
+    while let Some((least, most)) = rest
+        .split_whitespace()
+        .next()
+        .and_then(texture_option_arguments)
+    {
+        rest = skip_word(rest);
+        for i in 0..most {
+            let Some(argument) = rest.split_whitespace().next() else {
+                break;
             };
-            materials.push(Material::new(
-                material.ambient.unwrap_or([0.5, 0.5, 0.5]),
-                material.diffuse.unwrap_or([0.5, 0.5, 0.5]),
-                material.specular.unwrap_or([0.5, 0.5, 0.5]),
-                material.shininess.unwrap_or(32.0),
-                texture,
-            ));
+            if i >= least && argument.parse::<f32>().is_err() {
+                break;
+            }
+            rest = skip_word(rest);
         }
+    }
+    return Some(rest).filter(|file| !file.is_empty());
+}
+
+/// Value of the `-bm` option of a bump or normal map statement.
+fn bump_multiplier(statement: &str) -> Option<f32> {
+    let mut words = statement.split_whitespace();
+    words.find(|word| *word == "-bm")?;
+    return words.next()?.parse().ok();
+}
+
+/// Sampling of a texture statement, `-clamp on` clamps instead of repeating.
+fn texture_sampler(statement: &str) -> SamplerOptions {
+    let mut words = statement.split_whitespace();
+    return match words
+        .find(|word| *word == "-clamp")
+        .and_then(|_| words.next())
+    {
+        Some("on") => SamplerOptions::clamped(),
+        _ => SamplerOptions::default(),
+    };
+}
+
+/// Opens the texture of an MTL statement sampled as its options say, `None` without a file.
+fn open_mtl_texture(
+    statement: Option<&str>,
+    base_dir: &Path,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    encoder: &mut wgpu::CommandEncoder,
+    mipmaps: &MipmapGenerator,
+    format: wgpu::TextureFormat,
+) -> Result<Option<Texture>> {
+    let Some(statement) = statement else {
+        return Ok(None);
+    };
+    return texture_file(statement)
+        .map(|file| {
+            let options = texture_sampler(statement);
+            Texture::open_with_options(
+                device,
+                queue,
+                encoder,
+                mipmaps,
+                base_dir.join(file),
+                format,
+                &options,
+            )
+        })
+        .transpose();
+}
+
+fn is_pbr_mtl(material: &tobj::Material) -> bool {
+    return MTL_PBR_KEYS
+        .iter()
+        .any(|key| material.unknown_param.contains_key(*key));
+}
+
+/// Loads an MTL material with its textures, shaded as PBR when it uses the PBR extension and
+/// as Blinn-Phong otherwise. `norm`, or `bump` when it is missing, is used as normal map.
+fn load_mtl(
+    material: &tobj::Material,
+    base_dir: &Path,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    encoder: &mut wgpu::CommandEncoder,
+    layouts: &Layouts,
+    mipmaps: &MipmapGenerator,
+) -> Result<Material> {
+    let diffuse_image = material
+        .diffuse_texture
+        .as_deref()
+        .and_then(texture_file)
+        .map(|file| Texture::open_image(base_dir.join(file)))
+        .transpose()?;
+    let translucent_texture = diffuse_image
+        .as_ref()
+        .is_some_and(|image| image.pixels().any(|pixel| pixel[3] < 255));
+    let diffuse = diffuse_image.map(|image| {
+        let options = texture_sampler(material.diffuse_texture.as_deref().unwrap_or_default());
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        Texture::load_texture_with_options(
+            device, queue, encoder, mipmaps, &image, format, &options,
+        )
+    });
+    let (shading, mut params, mut textures) = match is_pbr_mtl(material) {
+        true => (
+            Shading::Pbr,
+            pbr_mtl_params(material),
+            load_pbr_mtl_textures(material, base_dir, device, queue, encoder, mipmaps, diffuse)?,
+        ),
+        false => (
+            Shading::Phong,
+            phong_mtl_params(material),
+            load_phong_mtl_textures(material, base_dir, device, queue, encoder, mipmaps, diffuse)?,
+        ),
+    };
+    let mut open = |statement: Option<&str>, format| {
+        return open_mtl_texture(statement, base_dir, device, queue, encoder, mipmaps, format);
+    };
 
-        return Model { meshes, materials };
+    let emissive_texture = open(
+        mtl_param(material, "map_Ke"),
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+    )?;
+    // an emissive map without a colour is shown as it is
+    params.emissive = match (mtl_color(material, "Ke"), &emissive_texture) {
+        (Some(emissive), _) => emissive,
+        (None, Some(_)) => [1.0, 1.0, 1.0],
+        (None, None) => [0.0, 0.0, 0.0],
+    };
+    if let Some(texture) = emissive_texture {
+        textures.emissive = texture;
+    }
+    if let Some(texture) = open(
+        material.dissolve_texture.as_deref(),
+        wgpu::TextureFormat::Rgba8Unorm,
+    )? {
+        textures.alpha = texture;
+    }
+    let alpha_mode = mtl_alpha_mode(material, translucent_texture);
+    if alpha_mode == AlphaMode::Mask {
+        params.alpha_cutoff = DEFAULT_ALPHA_CUTOFF;
     }
+    let normal_map = mtl_param(material, "norm").or(material.normal_texture.as_deref());
+    if let Some(texture) = open(normal_map, wgpu::TextureFormat::Rgba8Unorm)? {
+        textures.normal = texture;
+        params.normal_scale = normal_map.and_then(bump_multiplier).unwrap_or(1.0);
+    }
+    let mut material = Material::with_textures(device, layouts, shading, params, textures);
+    material.alpha_mode = alpha_mode;
+    return Ok(material);
+}
+
+/// `map_d` is taken as a cutout mask, as for foliage, while `d`, `Tr` or an alpha channel in
+/// `map_Kd` blend.
+fn mtl_alpha_mode(material: &tobj::Material, translucent_texture: bool) -> AlphaMode {
+    if material.dissolve_texture.is_some() {
+        return AlphaMode::Mask;
+    }
+    if translucent_texture || mtl_dissolve(material).is_some_and(|dissolve| dissolve < 1.0) {
+        return AlphaMode::Blend;
+    }
+    return AlphaMode::Opaque;
+}
+
+/// Maps `Ka`, `Kd`, `Ks`, `Ns` and `d` onto Blinn-Phong parameters.
+fn phong_mtl_params(material: &tobj::Material) -> MaterialParams {
+    return MaterialParams {
+        ambient: material.ambient.unwrap_or([0.5, 0.5, 0.5]),
+        diffuse: material.diffuse.unwrap_or([0.5, 0.5, 0.5]),
+        specular: material.specular.unwrap_or([0.5, 0.5, 0.5]),
+        shininess: material.shininess.unwrap_or(32.0),
+        dissolve: mtl_dissolve(material).unwrap_or(1.0),
+        ..Default::default()
+    };
+}
+
+/// Opacity from `d`, or from its inverse `Tr` that some exporters write instead.
+fn mtl_dissolve(material: &tobj::Material) -> Option<f32> {
+    return material
+        .dissolve
+        .or_else(|| mtl_float(material, "Tr").map(|transparency| 1.0 - transparency));
+}
+
+/// Loads `map_Ks` and `map_Ns` next to the already loaded `map_Kd`.
+fn load_phong_mtl_textures(
+    material: &tobj::Material,
+    base_dir: &Path,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    encoder: &mut wgpu::CommandEncoder,
+    mipmaps: &MipmapGenerator,
+    diffuse: Option<Texture>,
+) -> Result<MaterialTextures> {
+    let texture = diffuse.unwrap_or_else(|| {
+        Texture::create_solid_color_texture(device, queue, DEFAULT_TEXTURE_COLOR)
+    });
+    let mut textures = MaterialTextures::new(device, queue, texture);
+    let mut open = |statement: &Option<String>, format| {
+        return open_mtl_texture(
+            statement.as_deref(),
+            base_dir,
+            device,
+            queue,
+            encoder,
+            mipmaps,
+            format,
+        );
+    };
+    if let Some(texture) = open(
+        &material.specular_texture,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+    )? {
+        textures.specular = texture;
+    }
+    if let Some(texture) = open(&material.shininess_texture, wgpu::TextureFormat::Rgba8Unorm)? {
+        textures.shininess = texture;
+    }
+    return Ok(textures);
+}
+
+/// Maps `Kd`, `d`, `Pm` and `Pr` onto PBR parameters.
+fn pbr_mtl_params(material: &tobj::Material) -> MaterialParams {
+    let [r, g, b] = material.diffuse.unwrap_or([1.0, 1.0, 1.0]);
+    return MaterialParams {
+        base_color: [r, g, b, mtl_dissolve(material).unwrap_or(1.0)],
+        metallic: mtl_float(material, "Pm").unwrap_or(0.0),
+        roughness: mtl_float(material, "Pr").unwrap_or(1.0),
+        ..Default::default()
+    };
+}
+
+/// Loads `map_Pm` and `map_Pr` next to the already loaded `map_Kd`.
+fn load_pbr_mtl_textures(
+    material: &tobj::Material,
+    base_dir: &Path,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    encoder: &mut wgpu::CommandEncoder,
+    mipmaps: &MipmapGenerator,
+    base_color: Option<Texture>,
+) -> Result<MaterialTextures> {
+    let base_color = base_color.unwrap_or_else(|| {
+        Texture::create_solid_color_texture(device, queue, [255, 255, 255, 255])
+    });
+    let mut textures = MaterialTextures::new(device, queue, base_color);
+    let mut open = |key: &str, format| {
+        return open_mtl_texture(
+            mtl_param(material, key),
+            base_dir,
+            device,
+            queue,
+            encoder,
+            mipmaps,
+            format,
+        );
+    };
+    if let Some(texture) = open("map_Pm", wgpu::TextureFormat::Rgba8Unorm)? {
+        textures.metallic = texture;
+    }
+    if let Some(texture) = open("map_Pr", wgpu::TextureFormat::Rgba8Unorm)? {
+        textures.roughness = texture;
+    }
+    return Ok(textures);
 }
 
 #[repr(C)]
@@ -89,33 +503,206 @@ pub struct MaterialParams {
     pub _padding2: u32,
     pub specular: [f32; 3],
     pub shininess: f32,
+    /// Linear base colour and alpha of PBR materials.
+    pub base_color: [f32; 4],
+    pub emissive: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+    /// How much the occlusion texture darkens ambient light, from 0 to 1.
+    pub occlusion_strength: f32,
+    /// Scales the x and y of the normal map, flattening or exaggerating it.
+    pub normal_scale: f32,
+    /// Opacity of Phong materials, PBR ones use the alpha of `base_color`.
+    pub dissolve: f32,
+    /// Fragments with a lower alpha are discarded, 0 keeps all of them. Used by
+    /// `AlphaMode::Mask`.
+    pub alpha_cutoff: f32,
+    pub _padding3: [u32; 3],
+}
+
+/// Colour of the texture used by materials without a diffuse map.
+pub const DEFAULT_TEXTURE_COLOR: [u8; 4] = [125, 125, 125, 255];
+
+/// Cutoff of `AlphaMode::Mask` materials that do not set one, as in glTF.
+pub const DEFAULT_ALPHA_CUTOFF: f32 = 0.5;
+
+/// How the alpha of a material is used.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// Alpha is ignored.
+    #[default]
+    Opaque,
+    /// Fragments below `MaterialParams::alpha_cutoff` are discarded, in shadow maps too.
+    Mask,
+    /// Drawn after every opaque mesh, blended back to front without writing depth.
+    Blend,
+}
+
+/// Lighting model a material is drawn with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Shading {
+    /// Blinn-Phong from the ambient, diffuse, specular and shininess parameters.
+    #[default]
+    Phong,
+    /// Cook-Torrance GGX from the base colour, metallic, roughness, emissive and occlusion
+    /// parameters.
+    Pbr,
+}
+
+/// Number of textures in `MaterialTextures`.
+pub const MATERIAL_TEXTURE_SLOTS: u32 = 9;
+
+/// Texture slots of a material, each multiplied with the matching factor of `MaterialParams`.
+/// Slots without a map hold a texture that leaves the factor as it is.
+pub struct MaterialTextures {
+    /// Diffuse colour of Phong materials, base colour of PBR ones.
+    pub diffuse: Texture,
+    /// Metalness in the blue channel, as glTF packs it together with roughness.
+    pub metallic: Texture,
+    /// Roughness in the green channel.
+    pub roughness: Texture,
+    pub emissive: Texture,
+    /// Ambient occlusion in the red channel.
+    pub occlusion: Texture,
+    /// Tangent space normal map, linear.
+    pub normal: Texture,
+    /// Specular colour of Phong materials.
+    pub specular: Texture,
+    /// Shininess of Phong materials in the red channel, linear.
+    pub shininess: Texture,
+    /// Opacity in the red channel, linear.
+    pub alpha: Texture,
+}
+
+impl MaterialTextures {
+    /// `diffuse` with every other slot left empty: white, and a flat normal map.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, diffuse: Texture) -> Self {
+        let white = Texture::create_solid_color_texture(device, queue, [255, 255, 255, 255]);
+        return MaterialTextures {
+            diffuse,
+            metallic: white.clone(),
+            roughness: white.clone(),
+            emissive: white.clone(),
+            occlusion: white.clone(),
+            specular: white.clone(),
+            shininess: white.clone(),
+            alpha: white,
+            normal: Texture::create_solid_color_texture_with_format(
+                device,
+                queue,
+                [128, 128, 255, 255],
+                wgpu::TextureFormat::Rgba8Unorm,
+            ),
+        };
+    }
+
+    /// Slots in binding order, texture `i` is bound at `2 * i` and its sampler at `2 * i + 1`.
+    pub fn slots(&self) -> [&Texture; MATERIAL_TEXTURE_SLOTS as usize] {
+        return [
+            &self.diffuse,
+            &self.metallic,
+            &self.roughness,
+            &self.emissive,
+            &self.occlusion,
+            &self.normal,
+            &self.specular,
+            &self.shininess,
+            &self.alpha,
+        ];
+    }
 }
 
 pub struct Material {
+    /// Selects the pipeline the material's meshes are drawn with.
+    pub shading: Shading,
+    /// `AlphaMode::Mask` also needs `params.alpha_cutoff` set.
+    pub alpha_mode: AlphaMode,
+    /// Change through `Material::set_params` so the uniform buffer stays in sync.
     pub params: MaterialParams,
-    pub diffuse_texture: texture::Texture,
+    /// Uniform buffer holding `params`, every material has its own so draws in one pass
+    /// do not overwrite each other's parameters.
+    pub uniform: Binding,
+    pub textures: MaterialTextures,
+    /// Bind group for `textures`, created once when the material is loaded.
+    pub texture_binding: TextureBinding,
 }
 
 impl Material {
+    /// Blinn-Phong material with a diffuse texture.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layouts: &Layouts,
         ambient: [f32; 3],
         diffuse: [f32; 3],
         specular: [f32; 3],
         shininess: f32,
         diffuse_texture: texture::Texture,
+    ) -> Self {
+        let params = MaterialParams {
+            ambient,
+            diffuse,
+            specular,
+            shininess,
+            ..Default::default()
+        };
+        return Material::with_textures(
+            device,
+            layouts,
+            Shading::Phong,
+            params,
+            MaterialTextures::new(device, queue, diffuse_texture),
+        );
+    }
+
+    pub fn with_textures(
+        device: &wgpu::Device,
+        layouts: &Layouts,
+        shading: Shading,
+        params: MaterialParams,
+        textures: MaterialTextures,
     ) -> Self {
         return Material {
-            params: MaterialParams {
-                ambient,
-                diffuse,
-                specular,
-                shininess,
-                _padding1: 0,
-                _padding2: 0,
-            },
-            diffuse_texture,
+            shading,
+            alpha_mode: AlphaMode::Opaque,
+            params,
+            uniform: Binding::create_binding(
+                device,
+                &layouts.material_bind_group_layout,
+                vec![bytemuck::cast_slice(&[params])],
+                2,
+            ),
+            texture_binding: TextureBinding::with_textures(
+                device,
+                &layouts.texture_bind_group_layout,
+                &textures.slots(),
+                1,
+            ),
+            textures,
         };
     }
+
+    pub fn set_params(&mut self, queue: &wgpu::Queue, params: MaterialParams) {
+        self.params = params;
+        self.uniform
+            .update_buffer(queue, vec![bytemuck::cast_slice(&[params])]);
+    }
+
+    /// Material used for meshes that do not reference one.
+    pub fn fallback(device: &wgpu::Device, queue: &wgpu::Queue, layouts: &Layouts) -> Self {
+        let texture = Texture::create_solid_color_texture(device, queue, DEFAULT_TEXTURE_COLOR);
+        return Material::new(
+            device,
+            queue,
+            layouts,
+            [0.5, 0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            32.0,
+            texture,
+        );
+    }
 }
 
 impl Bindeable for MaterialParams {
@@ -128,7 +715,9 @@ impl Bindeable for MaterialParams {
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
-                    min_binding_size: wgpu::BufferSize::new(48),
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<MaterialParams>() as u64
+                    ),
                 },
                 count: None,
             }],
@@ -145,11 +734,25 @@ impl default::Default for MaterialParams {
             _padding2: 0,
             specular: [0.5, 0.5, 0.5],
             shininess: 0.5,
+            base_color: [1.0, 1.0, 1.0, 1.0],
+            emissive: [0.0, 0.0, 0.0],
+            metallic: 0.0,
+            roughness: 1.0,
+            occlusion_strength: 1.0,
+            normal_scale: 1.0,
+            dissolve: 1.0,
+            alpha_cutoff: 0.0,
+            _padding3: [0; 3],
         };
     }
 }
 
-pub fn create_plane(device: &wgpu::Device, queue: &wgpu::Queue, color: [u8; 4]) -> Model {
+pub fn create_plane(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layouts: &Layouts,
+    color: [u8; 4],
+) -> Model {
     let mut verticies: Vec<Vertex> = Vec::new();
     let mut indicies: Vec<u32> = Vec::new();
     for i in -10..10 {
@@ -172,10 +775,13 @@ pub fn create_plane(device: &wgpu::Device, queue: &wgpu::Queue, color: [u8; 4])
         vertex_buffer,
         index_buffer,
         len_indicies: indicies.len(),
-        material_id: Some(0),
+        material_id: 0,
     };
     let texture = Texture::create_solid_color_texture(device, queue, color);
     let material = Material::new(
+        device,
+        queue,
+        layouts,
         [0.5, 0.5, 0.5],
         [0.5, 0.5, 0.5],
         [0.5, 0.5, 0.5],
@@ -187,3 +793,102 @@ pub fn create_plane(device: &wgpu::Device, queue: &wgpu::Queue, color: [u8; 4])
         materials: vec![material],
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mtl(params: &[(&str, &str)]) -> tobj::Material {
+        return tobj::Material {
+            unknown_param: params
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+    }
+
+    #[test]
+    fn pbr_extension_selects_pbr() {
+        assert!(is_pbr_mtl(&mtl(&[("Pr", "0.5")])));
+        assert!(is_pbr_mtl(&mtl(&[("map_Pm", "metal.png")])));
+        assert!(!is_pbr_mtl(&mtl(&[("Ke", "1 1 1")])));
+    }
+
+    #[test]
+    fn parses_pbr_statements() {
+        let material = mtl(&[
+            ("Pr", " 0.25 "),
+            ("Ke", "1.0 0.5 0.0"),
+            ("Tf", "0.5"),
+            ("map_Pr", "-bm 0.5 -clamp on textures/rough.png"),
+        ]);
+        assert_eq!(mtl_float(&material, "Pr"), Some(0.25));
+        assert_eq!(mtl_float(&material, "Pm"), None);
+        assert_eq!(mtl_color(&material, "Ke"), Some([1.0, 0.5, 0.0]));
+        assert_eq!(mtl_color(&material, "Tf"), Some([0.5, 0.5, 0.5]));
+        let map_pr = mtl_param(&material, "map_Pr").unwrap();
+        assert_eq!(texture_file(map_pr), Some("textures/rough.png"));
+        assert_eq!(texture_sampler(map_pr), SamplerOptions::clamped());
+        assert_eq!(
+            texture_sampler("-clamp off rough.png"),
+            SamplerOptions::default()
+        );
+    }
+
+    #[test]
+    fn texture_file_skips_options() {
+        assert_eq!(texture_file("my texture.png"), Some("my texture.png"));
+        assert_eq!(
+            texture_file("-bm 0.5 -clamp on my  texture.png"),
+            Some("my  texture.png")
+        );
+        assert_eq!(texture_file("-s 2 2 1 -o 0.5 tex.png"), Some("tex.png"));
+        assert_eq!(texture_file("-s 2 tex.png"), Some("tex.png"));
+        assert_eq!(texture_file("-mm 0 1 -imfchan l 1.png"), Some("1.png"));
+        assert_eq!(texture_file("-clamp on"), None);
+    }
+
+    #[test]
+    fn parses_phong_statements() {
+        let material = tobj::Material {
+            specular: Some([0.25, 0.25, 0.25]),
+            shininess: Some(64.0),
+            specular_texture: Some("-clamp on textures/specular.png".to_string()),
+            ..mtl(&[("Ke", "0.5"), ("Tr", "0.25")])
+        };
+        let params = phong_mtl_params(&material);
+        assert_eq!(params.specular, [0.25, 0.25, 0.25]);
+        assert_eq!(params.shininess, 64.0);
+        assert_eq!(params.dissolve, 0.75);
+        assert_eq!(mtl_color(&material, "Ke"), Some([0.5, 0.5, 0.5]));
+        assert_eq!(
+            material.specular_texture.as_deref().and_then(texture_file),
+            Some("textures/specular.png")
+        );
+
+        let opaque = tobj::Material {
+            dissolve: Some(1.0),
+            ..mtl(&[("Tr", "0.25")])
+        };
+        assert_eq!(phong_mtl_params(&opaque).dissolve, 1.0);
+    }
+
+    #[test]
+    fn mtl_alpha_modes() {
+        let foliage = tobj::Material {
+            dissolve_texture: Some("leaf_mask.png".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(mtl_alpha_mode(&foliage, true), AlphaMode::Mask);
+        assert_eq!(
+            mtl_alpha_mode(&mtl(&[("Tr", "0.5")]), false),
+            AlphaMode::Blend
+        );
+        assert_eq!(mtl_alpha_mode(&mtl(&[]), true), AlphaMode::Blend);
+        assert_eq!(
+            mtl_alpha_mode(&mtl(&[("Tr", "0")]), false),
+            AlphaMode::Opaque
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use winit::dpi::PhysicalSize;
+
+use crate::error::Result;
+
+pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Color target used instead of a surface texture when rendering without a window.
+/// Every rendered frame is copied into `readback_buffer` so it can be mapped on the CPU.
+#[derive(Clone)]
+pub struct Offscreen {
+    pub texture: wgpu::Texture,
+    pub texture_view: wgpu::TextureView,
+    pub readback_buffer: wgpu::Buffer,
+    pub padded_bytes_per_row: u32,
+    pub size: PhysicalSize<u32>,
+}
+
+impl Offscreen {
+    pub fn new(device: &wgpu::Device, size: PhysicalSize<u32>) -> Offscreen {
+        let size = PhysicalSize::new(size.width.max(1), size.height.max(1));
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: OFFSCREEN_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // rows copied into a buffer have to be aligned to COPY_BYTES_PER_ROW_ALIGNMENT
+        let padded_bytes_per_row = (4 * size.width).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (padded_bytes_per_row * size.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        return Offscreen {
+            texture,
+            texture_view,
+            readback_buffer,
+            padded_bytes_per_row,
+            size,
+        };
+    }
+
+    pub fn copy_to_buffer(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.readback_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.size.height),
+                },
+            },
+            self.texture.size(),
+        );
+    }
+
+    /// Blocks until the last copied frame is available and returns it without row padding.
+    pub fn read_image(&self, device: &wgpu::Device) -> Result<image::RgbaImage> {
+        let buffer_slice = self.readback_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv().unwrap_or(Err(wgpu::BufferAsyncError))?;
+
+        let row_len = (4 * self.size.width) as usize;
+        let mut pixels: Vec<u8> = Vec::with_capacity(row_len * self.size.height as usize);
+        {
+            let padded = buffer_slice.get_mapped_range();
+            for row in padded.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..row_len]);
+            }
+        }
+        self.readback_buffer.unmap();
+        return Ok(image::RgbaImage::from_raw(self.size.width, self.size.height, pixels).unwrap());
+    }
+}
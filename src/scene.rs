@@ -0,0 +1,98 @@
+use crate::instance::Instance;
+use crate::light::{DirectionalLight, Light, SpotLight};
+use crate::model::{AlphaMode, Model};
+
+/// Everything that gets drawn: models with their instances and the lights illuminating them.
+pub struct Scene {
+    pub models: Vec<(Model, Vec<Instance>)>,
+    /// Point lights, only the first `light::MAX_LIGHTS` are shaded.
+    pub lights: Vec<Light>,
+    /// Directional light with cascaded shadows, on top of the point lights.
+    pub sun: Option<DirectionalLight>,
+    /// Spot lights, only the first `light::MAX_SPOT_LIGHTS` are shaded.
+    pub spot_lights: Vec<SpotLight>,
+}
+
+impl Scene {
+    pub fn new(lights: Vec<Light>) -> Scene {
+        return Scene {
+            models: Vec::new(),
+            lights,
+            sun: None,
+            spot_lights: Vec::new(),
+        };
+    }
+
+    /// Adds a model drawn once per instance and returns its index in `models`.
+    pub fn add_model(&mut self, model: Model, instances: Vec<Instance>) -> usize {
+        self.models.push((model, instances));
+        return self.models.len() - 1;
+    }
+
+    /// Adds a light and returns its index in `lights`.
+    pub fn add_light(&mut self, light: Light) -> usize {
+        self.lights.push(light);
+        return self.lights.len() - 1;
+    }
+
+    /// Adds a spot light and returns its index in `spot_lights`.
+    pub fn add_spot_light(&mut self, light: SpotLight) -> usize {
+        self.spot_lights.push(light);
+        return self.spot_lights.len() - 1;
+    }
+
+    pub fn remove_light(&mut self, light_index: usize) -> Light {
+        return self.lights.remove(light_index);
+    }
+
+    /// Instances of models with blended meshes as `(model index, instance index)`, the farthest
+    /// from `eye` first.
+    pub fn transparent_instances(&self, eye: glam::Vec3) -> Vec<(usize, u32)> {
+        let mut transparent: Vec<(f32, usize, u32)> = Vec::new();
+        for (model_index, (model, instances)) in self.models.iter().enumerate() {
+            let blended = model
+                .materials
+                .iter()
+                .any(|material| material.alpha_mode == AlphaMode::Blend);
+            if !blended {
+                continue;
+            }
+            for (instance_index, instance) in instances.iter().enumerate() {
+                let distance = instance.position().distance_squared(eye);
+                transparent.push((distance, model_index, instance_index as u32));
+            }
+        }
+        transparent.sort_by(|a, b| b.0.total_cmp(&a.0));
+        return transparent
+            .into_iter()
+            .map(|(_, model_index, instance_index)| (model_index, instance_index))
+            .collect();
+    }
+
+    /// Rotates every instance of the model at `model_index` around the world origin, if it exists.
+    pub fn rotate_instances(&mut self, model_index: usize, rotation: glam::f32::Quat) {
+        if let Some((_, instances)) = self.models.get_mut(model_index) {
+            for instance in instances.iter_mut() {
+                *instance = rotation * *instance
+            }
+        }
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        return Scene::new(vec![Light::new([-1.0, 6.0, 1.0], [1.0, 1.0, 1.0])]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotating_missing_model_is_ignored() {
+        let mut scene = Scene::new(Vec::new());
+        scene.rotate_instances(0, glam::Quat::from_rotation_y(1.0));
+        assert!(scene.models.is_empty());
+    }
+}
@@ -0,0 +1,448 @@
+//! Scenes described in RON files.
+//!
+//! ```ron
+//! (
+//!     camera: (position: (0.0, 1.0, 5.0), pitch: 0.0, yaw: -90.0),
+//!     lights: [
+//!         (position: (-1.0, 6.0, 1.0), color: (1.0, 1.0, 1.0)),
+//!         (position: (3.0, 2.0, -2.0), color: (1.0, 0.5, 0.2), intensity: 0.5, range: 10.0),
+//!         (position: (0.0, 40.0, 0.0), range: 200.0, shadow: (near: 1.0, depth_bias: 0.1)),
+//!     ],
+//!     spot_lights: [
+//!         (position: (0.0, 3.0, 0.0), direction: (0.0, -1.0, 0.0), inner_angle: 15.0, outer_angle: 25.0),
+//!     ],
+//!     sun: Some((direction: (-0.3, -1.0, -0.5), color: (1.0, 0.9, 0.8), intensity: 0.8)),
+//!     models: [
+//!         (path: "model.obj", instances: [(translation: (1.0, 1.0, 1.0), scale: (0.33, 0.33, 0.33))]),
+//!     ],
+//!     planes: [
+//!         (color: (124, 102, 92, 255), instances: [(translation: (0.0, -1.0, 0.0))]),
+//!     ],
+//! )
+//! ```
+//!
+//! Angles are in degrees, rotations are XYZ euler angles. Model paths are relative to the scene file
+//! and point at Wavefront `.obj` or glTF `.gltf`/`.glb` files, glTF node transforms are applied
+//! on top of each instance.
+//! Models without normals get smooth ones generated, `normals: Flat` on a model asks for faceted ones.
+//! Every light takes an optional `shadow` with its shadow map near plane and biases, see
+//! `ShadowSettings`.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::camera::Camera;
+use crate::error::{Error, Result};
+use crate::gltf_loader::{is_gltf, load_gltf};
+use crate::instance::Instance;
+use crate::layouts::Layouts;
+use crate::light::{DirectionalLight, Light, ShadowSettings, SpotLight};
+use crate::mipmap::MipmapGenerator;
+use crate::model::{create_plane, GeneratedNormals, ImportOptions, Model};
+use crate::scene::Scene;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraDesc {
+    pub position: [f32; 3],
+    pub pitch: f32,
+    pub yaw: f32,
+    pub speed: f32,
+    pub fov: f32,
+}
+
+impl CameraDesc {
+    pub fn to_camera(&self, aspect_ratio: f32) -> Camera {
+        return Camera::new(
+            self.position.into(),
+            self.pitch.to_radians(),
+            self.yaw.to_radians(),
+            self.speed,
+            self.fov.to_radians(),
+            aspect_ratio,
+        );
+    }
+}
+
+impl Default for CameraDesc {
+    fn default() -> Self {
+        return CameraDesc {
+            position: [0.0, 1.0, 5.0],
+            pitch: 0.0,
+            yaw: -90.0,
+            speed: 2.5,
+            fov: 60.0,
+        };
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransformDesc {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 3],
+    pub scale: [f32; 3],
+}
+
+impl TransformDesc {
+    pub fn to_instance(&self) -> Instance {
+        let rotation = glam::Quat::from_euler(
+            glam::EulerRot::XYZ,
+            self.rotation[0].to_radians(),
+            self.rotation[1].to_radians(),
+            self.rotation[2].to_radians(),
+        );
+        return Instance::from_translation_rotation_scale(
+            self.translation.into(),
+            rotation,
+            self.scale.into(),
+        );
+    }
+}
+
+impl Default for TransformDesc {
+    fn default() -> Self {
+        return TransformDesc {
+            translation: [0.0, 0.0, 0.0],
+            rotation: [0.0, 0.0, 0.0],
+            scale: [1.0, 1.0, 1.0],
+        };
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightDesc {
+    pub position: [f32; 3],
+    #[serde(default = "white")]
+    pub color: [f32; 3],
+    #[serde(default = "one")]
+    pub intensity: f32,
+    /// Distance at which the light has faded out.
+    #[serde(default = "default_range")]
+    pub range: f32,
+    #[serde(default)]
+    pub shadow: ShadowSettings,
+}
+
+fn white() -> [f32; 3] {
+    return [1.0, 1.0, 1.0];
+}
+
+fn one() -> f32 {
+    return 1.0;
+}
+
+fn default_range() -> f32 {
+    return 100.0;
+}
+
+impl LightDesc {
+    pub fn to_light(&self) -> Light {
+        let mut light = Light::new(self.position, self.color);
+        light.intensity = self.intensity;
+        light.range = self.range;
+        light.shadow = self.shadow;
+        return light;
+    }
+}
+
+impl Default for LightDesc {
+    fn default() -> Self {
+        return LightDesc {
+            position: [-1.0, 6.0, 1.0],
+            color: white(),
+            intensity: one(),
+            range: default_range(),
+            shadow: ShadowSettings::default(),
+        };
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpotLightDesc {
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    /// Cone angle in degrees, measured from the direction, lit at full strength.
+    #[serde(default = "default_inner_angle")]
+    pub inner_angle: f32,
+    /// Cone angle in degrees past which nothing is lit.
+    #[serde(default = "default_outer_angle")]
+    pub outer_angle: f32,
+    #[serde(default = "white")]
+    pub color: [f32; 3],
+    #[serde(default = "one")]
+    pub intensity: f32,
+    #[serde(default = "default_range")]
+    pub range: f32,
+    #[serde(default)]
+    pub shadow: ShadowSettings,
+}
+
+fn default_inner_angle() -> f32 {
+    return 20.0;
+}
+
+fn default_outer_angle() -> f32 {
+    return 30.0;
+}
+
+impl SpotLightDesc {
+    pub fn to_light(&self) -> SpotLight {
+        let mut light = SpotLight::new(self.position, self.direction, self.color);
+        light.inner_angle = self.inner_angle.to_radians();
+        light.outer_angle = self.outer_angle.to_radians();
+        light.intensity = self.intensity;
+        light.range = self.range;
+        light.shadow = self.shadow;
+        return light;
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SunDesc {
+    /// Direction the light shines in, it does not have to be normalized.
+    pub direction: [f32; 3],
+    #[serde(default = "white")]
+    pub color: [f32; 3],
+    #[serde(default = "one")]
+    pub intensity: f32,
+    #[serde(default)]
+    pub shadow: ShadowSettings,
+}
+
+impl SunDesc {
+    pub fn to_light(&self) -> DirectionalLight {
+        let mut light = DirectionalLight::new(self.direction, self.color);
+        light.intensity = self.intensity;
+        light.shadow = self.shadow;
+        return light;
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelDesc {
+    pub path: PathBuf,
+    /// Normals generated when the model has none, `Smooth` or `Flat`.
+    #[serde(default)]
+    pub normals: GeneratedNormals,
+    pub instances: Vec<TransformDesc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlaneDesc {
+    pub color: [u8; 4],
+    pub instances: Vec<TransformDesc>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneFile {
+    pub camera: CameraDesc,
+    /// Point lights, a scene without any gets a single default light.
+    pub lights: Vec<LightDesc>,
+    pub spot_lights: Vec<SpotLightDesc>,
+    /// Directional light, none by default.
+    pub sun: Option<SunDesc>,
+    pub models: Vec<ModelDesc>,
+    pub planes: Vec<PlaneDesc>,
+    /// Directory model paths are resolved against.
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+impl SceneFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<SceneFile> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let mut scene_file = SceneFile::parse(&source).map_err(|err| match err {
+            Error::Scene {
+                line,
+                column,
+                message,
+                ..
+            } => Error::Scene {
+                path: Some(path.to_owned()),
+                line,
+                column,
+                message,
+            },
+            err => err,
+        })?;
+        scene_file.base_dir = path.parent().map(Path::to_owned).unwrap_or_default();
+        return Ok(scene_file);
+    }
+
+    /// A scene with just the model at `path`, at the origin and lit by the default light.
+    pub fn for_model(path: impl AsRef<Path>) -> SceneFile {
+        return SceneFile {
+            models: vec![ModelDesc {
+                path: path.as_ref().to_owned(),
+                normals: GeneratedNormals::default(),
+                instances: vec![TransformDesc::default()],
+            }],
+            ..Default::default()
+        };
+    }
+
+    pub fn parse(source: &str) -> Result<SceneFile> {
+        return ron::from_str(source).map_err(|err| Error::Scene {
+            path: None,
+            line: err.span.start.line,
+            column: err.span.start.col,
+            message: err.code.to_string(),
+        });
+    }
+
+    /// The file's point lights, or a default one when the file has no light at all.
+    fn point_lights(&self) -> Vec<Light> {
+        if self.lights.is_empty() && self.spot_lights.is_empty() && self.sun.is_none() {
+            return vec![LightDesc::default().to_light()];
+        }
+        return self.lights.iter().map(LightDesc::to_light).collect();
+    }
+
+    /// Loads every model and builds the `Scene` the file describes.
+    pub fn build(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layouts: &Layouts,
+        mipmaps: &MipmapGenerator,
+    ) -> Result<Scene> {
+        let mut scene = Scene::new(self.point_lights());
+        scene.sun = self.sun.as_ref().map(SunDesc::to_light);
+        scene.spot_lights = self
+            .spot_lights
+            .iter()
+            .map(SpotLightDesc::to_light)
+            .collect();
+        for model in self.models.iter() {
+            let path = self.base_dir.join(&model.path);
+            let options = ImportOptions {
+                normals: model.normals,
+            };
+            let instances = model
+                .instances
+                .iter()
+                .map(TransformDesc::to_instance)
+                .collect::<Vec<_>>();
+            if !is_gltf(&path) {
+                scene.add_model(
+                    Model::load_model_with(&path, device, queue, layouts, mipmaps, &options)?,
+                    instances,
+                );
+                continue;
+            }
+            for (gltf_model, node_instances) in
+                load_gltf(&path, device, queue, layouts, mipmaps, &options)?
+            {
+                let instances = instances
+                    .iter()
+                    .flat_map(|instance| node_instances.iter().map(|node| *instance * *node))
+                    .collect();
+                scene.add_model(gltf_model, instances);
+            }
+        }
+        for plane in self.planes.iter() {
+            scene.add_model(
+                create_plane(device, queue, layouts, plane.color),
+                plane
+                    .instances
+                    .iter()
+                    .map(TransformDesc::to_instance)
+                    .collect(),
+            );
+        }
+        return Ok(scene);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_demo_scene() {
+        let scene_file =
+            SceneFile::open(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/scene.ron")).unwrap();
+        assert_eq!(scene_file.models.len(), 1);
+        assert_eq!(scene_file.models[0].instances.len(), 3);
+        assert_eq!(scene_file.planes.len(), 3);
+        assert_eq!(scene_file.lights[0].position, [-1.0, 6.0, 1.0]);
+    }
+
+    #[test]
+    fn parses_several_lights() {
+        let source =
+            "(lights: [(position: (0.0, 1.0, 0.0)), (position: (2.0, 1.0, 0.0), range: 5.0)])";
+        let lights = SceneFile::parse(source).unwrap().lights;
+        assert_eq!(lights.len(), 2);
+        assert_eq!(lights[0].range, 100.0);
+        assert_eq!(lights[1].to_light().range, 5.0);
+        assert_eq!(lights[1].shadow, ShadowSettings::default());
+    }
+
+    #[test]
+    fn parses_shadow_settings() {
+        let source =
+            "(lights: [(position: (0.0, 1.0, 0.0), shadow: (near: 0.5, normal_bias: 0.1))])";
+        let shadow = SceneFile::parse(source).unwrap().lights[0]
+            .to_light()
+            .shadow;
+        assert_eq!(shadow.near, 0.5);
+        assert_eq!(shadow.normal_bias, 0.1);
+        assert_eq!(shadow.depth_bias, ShadowSettings::default().depth_bias);
+    }
+
+    #[test]
+    fn parses_sun() {
+        let source = "(sun: Some((direction: (0.0, -1.0, 0.0), intensity: 0.5)))";
+        let sun = SceneFile::parse(source).unwrap().sun.unwrap().to_light();
+        assert_eq!(sun.direction, [0.0, -1.0, 0.0]);
+        assert_eq!(sun.color, [1.0, 1.0, 1.0]);
+        assert_eq!(sun.intensity, 0.5);
+    }
+
+    #[test]
+    fn sun_only_scene_has_no_point_light() {
+        let source = "(sun: Some((direction: (0.0, -1.0, 0.0))))";
+        assert!(SceneFile::parse(source).unwrap().point_lights().is_empty());
+        assert_eq!(SceneFile::parse("()").unwrap().point_lights().len(), 1);
+    }
+
+    #[test]
+    fn spot_lit_scene_has_no_point_light() {
+        let source = "(spot_lights: [(position: (0.0, 3.0, 0.0), direction: (0.0, -1.0, 0.0))])";
+        assert!(SceneFile::parse(source).unwrap().point_lights().is_empty());
+    }
+
+    #[test]
+    fn parses_spot_lights() {
+        let source = "(spot_lights: [(position: (0.0, 3.0, 0.0), direction: (0.0, -1.0, 0.0), outer_angle: 40.0)])";
+        let light = SceneFile::parse(source).unwrap().spot_lights[0].to_light();
+        assert_eq!(light.inner_angle, 20.0f32.to_radians());
+        assert_eq!(light.outer_angle, 40.0f32.to_radians());
+    }
+
+    #[test]
+    fn reports_error_position() {
+        let source =
+            "(\n    models: [\n        (path: \"a.obj\", instances: [(scale: 1.0)]),\n    ],\n)";
+        match SceneFile::parse(source) {
+            Err(Error::Scene { line, column, .. }) => {
+                assert_eq!(line, 3);
+                assert!(column > 1);
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+}
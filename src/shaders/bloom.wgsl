@@ -0,0 +1,96 @@
+
+struct Params {
+  threshold: f32,
+  // width of the soft transition below the threshold
+  knee: f32,
+  intensity: f32,
+  radius: f32
+}
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+
+struct VertexOutput {
+  @builtin(position) position: vec4<f32>,
+  @location(0) uv: vec2<f32>
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: VertexOutput;
+    output.position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return output;
+}
+
+
+// 13 tap downsample from Jimenez, "Next generation post processing in Call of Duty"
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+    let a = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(-2.0, 2.0)).rgb;
+    let b = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(0.0, 2.0)).rgb;
+    let c = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(2.0, 2.0)).rgb;
+    let d = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(-2.0, 0.0)).rgb;
+    let e = textureSample(source_texture, source_sampler, uv).rgb;
+    let f = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(2.0, 0.0)).rgb;
+    let g = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(-2.0, -2.0)).rgb;
+    let h = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(0.0, -2.0)).rgb;
+    let i = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(2.0, -2.0)).rgb;
+    let j = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(-1.0, 1.0)).rgb;
+    let k = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(1.0, 1.0)).rgb;
+    let l = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(-1.0, -1.0)).rgb;
+    let m = textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(1.0, -1.0)).rgb;
+    return e * 0.125 + (a + c + g + i) * 0.03125 + (b + d + f + h) * 0.0625 + (j + k + l + m) * 0.125;
+}
+
+// 9 tap tent filter, `radius` scales it in texels of the level being sampled
+fn upsample(uv: vec2<f32>) -> vec3<f32> {
+    let offset = params.radius / vec2<f32>(textureDimensions(source_texture));
+    var color = textureSample(source_texture, source_sampler, uv).rgb * 4.0;
+    color += textureSample(source_texture, source_sampler, uv + offset * vec2<f32>(-1.0, 0.0)).rgb * 2.0;
+    color += textureSample(source_texture, source_sampler, uv + offset * vec2<f32>(1.0, 0.0)).rgb * 2.0;
+    color += textureSample(source_texture, source_sampler, uv + offset * vec2<f32>(0.0, -1.0)).rgb * 2.0;
+    color += textureSample(source_texture, source_sampler, uv + offset * vec2<f32>(0.0, 1.0)).rgb * 2.0;
+    color += textureSample(source_texture, source_sampler, uv + offset * vec2<f32>(-1.0, -1.0)).rgb;
+    color += textureSample(source_texture, source_sampler, uv + offset * vec2<f32>(1.0, -1.0)).rgb;
+    color += textureSample(source_texture, source_sampler, uv + offset * vec2<f32>(-1.0, 1.0)).rgb;
+    color += textureSample(source_texture, source_sampler, uv + offset * vec2<f32>(1.0, 1.0)).rgb;
+    return color / 16.0;
+}
+
+// keeps what is brighter than the threshold, with a quadratic fade in over the knee
+fn threshold(color: vec3<f32>) -> vec3<f32> {
+    let brightness = max(color.r, max(color.g, color.b));
+    var soft = clamp(brightness - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+    soft = soft * soft / (4.0 * params.knee + 1e-4);
+    let contribution = max(soft, brightness - params.threshold) / max(brightness, 1e-4);
+    return color * contribution;
+}
+
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(threshold(downsample(in.uv)), 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.uv), 1.0);
+}
+
+// added onto the next larger level
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(in.uv), 1.0);
+}
+
+// added onto the frame
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(in.uv) * params.intensity, 1.0);
+}
@@ -0,0 +1,85 @@
+
+const BIN_COUNT: u32 = 256u;
+// mid grey the average luminance is exposed to
+const KEY_VALUE: f32 = 0.18;
+
+struct Params {
+  min_log_luminance: f32,
+  log_luminance_range: f32,
+  // fraction of the way from the previous to the measured luminance covered this frame
+  adaptation: f32,
+  pixel_count: u32
+}
+
+struct Exposure {
+  average_luminance: f32,
+  exposure: f32
+}
+
+@group(0) @binding(0)
+var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> params: Params;
+@group(0) @binding(2)
+var<storage, read_write> histogram: array<atomic<u32>, BIN_COUNT>;
+@group(0) @binding(3)
+var<storage, read_write> exposure: Exposure;
+
+var<workgroup> local_bins: array<atomic<u32>, BIN_COUNT>;
+var<workgroup> weighted_bins: array<f32, BIN_COUNT>;
+
+
+// bin 0 holds pixels too dark for the histogram range, they are left out of the average
+fn luminance_bin(color: vec3<f32>) -> u32 {
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let log_luminance = (log2(luminance) - params.min_log_luminance) / params.log_luminance_range;
+    if luminance < exp2(params.min_log_luminance) {
+        return 0u;
+    }
+    return u32(clamp(log_luminance, 0.0, 1.0) * f32(BIN_COUNT - 2u) + 1.0);
+}
+
+@compute @workgroup_size(16, 16)
+fn build_histogram(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(local_invocation_index) index: u32,
+) {
+    atomicStore(&local_bins[index], 0u);
+    workgroupBarrier();
+    if all(id.xy < textureDimensions(hdr_texture)) {
+        let color = textureLoad(hdr_texture, vec2<i32>(id.xy), 0).rgb;
+        atomicAdd(&local_bins[luminance_bin(color)], 1u);
+    }
+    workgroupBarrier();
+    atomicAdd(&histogram[index], atomicLoad(&local_bins[index]));
+}
+
+// single workgroup, one invocation per bin, also clears the histogram for the next frame
+@compute @workgroup_size(256)
+fn average_histogram(@builtin(local_invocation_index) index: u32) {
+    let count = atomicLoad(&histogram[index]);
+    weighted_bins[index] = f32(count) * f32(index);
+    atomicStore(&histogram[index], 0u);
+    workgroupBarrier();
+    for (var stride = BIN_COUNT / 2u; stride > 0u; stride >>= 1u) {
+        if index < stride {
+            weighted_bins[index] += weighted_bins[index + stride];
+        }
+        workgroupBarrier();
+    }
+    if index != 0u {
+        return;
+    }
+    // `count` is the number of pixels in bin 0 here
+    let lit_pixels = max(f32(params.pixel_count) - f32(count), 1.0);
+    let average_bin = weighted_bins[0] / lit_pixels - 1.0;
+    let log_luminance = average_bin / f32(BIN_COUNT - 2u) * params.log_luminance_range + params.min_log_luminance;
+    let measured = exp2(clamp(log_luminance, params.min_log_luminance, params.min_log_luminance + params.log_luminance_range));
+    var average = measured;
+    // the first frame starts from the measured luminance instead of fading in from black
+    if exposure.average_luminance > 0.0 {
+        average = mix(exposure.average_luminance, measured, params.adaptation);
+    }
+    exposure.average_luminance = average;
+    exposure.exposure = KEY_VALUE / average;
+}
@@ -13,7 +13,8 @@ struct VertexOutput {
 
 struct Camera {
   view_matrix: mat4x4<f32>,
-  pos: vec4<f32>
+  pos: vec4<f32>,
+  direction: vec4<f32>
 }
 
 @group(0)
@@ -21,24 +22,42 @@ struct Camera {
 var<uniform> camera: Camera;
 
 
+struct ShadowSettings {
+  near: f32,
+  depth_bias: f32,
+  slope_bias: f32,
+  normal_bias: f32
+}
+
 struct Light {
   position: vec3<f32>,
-  color: vec3<f32>
+  range: f32,
+  color: vec3<f32>,
+  intensity: f32,
+  shadow: ShadowSettings,
+  view_proj: mat4x4<f32>
 }
 
+struct Lights {
+  count: u32,
+  lights: array<Light, 16>
+}
 
 @group(0)
 @binding(1)
-var<uniform> light: Light;
+var<uniform> lights: Lights;
 
 
+// one instance per light
 @vertex
-fn vs_main(v_in: VertexInput) -> VertexOutput {
+fn vs_main(v_in: VertexInput, @builtin(instance_index) instance: u32) -> VertexOutput {
     let scale = 0.25;
+    let light = lights.lights[instance];
     var output: VertexOutput;
     let world_pos = v_in.position * scale + light.position;
     output.position = camera.view_matrix * vec4<f32>(world_pos, 1.0);
-    output.color = light.color;
+    // brighter than 1 for intense lights, so they can bloom
+    output.color = light.color * light.intensity;
     return output;
 }
 
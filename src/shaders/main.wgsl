@@ -6,6 +6,7 @@ struct VertexInput {
   @location(4) instance_transform_1: vec4<f32>,
   @location(5) instance_transform_2: vec4<f32>,
   @location(6) instance_transform_3: vec4<f32>,
+  @location(7) tangent: vec4<f32>,
 }
 
 
@@ -13,27 +14,104 @@ struct VertexOutput {
   @builtin(position) clip_position: vec4<f32>,
   @location(0) tex_cords: vec2<f32>,
   @location(1) normal: vec3<f32>,
-  @location(2) world_position: vec4<f32>
-
+  @location(2) world_position: vec4<f32>,
+  // world space, w is the handedness of the bitangent
+  @location(3) tangent: vec4<f32>
 }
 
 struct Camera {
   view_matrix: mat4x4<f32>,
-  pos: vec4<f32>
+  pos: vec4<f32>,
+  direction: vec4<f32>
 }
 
 @group(0)
 @binding(0)
 var<uniform> camera: Camera;
 
+// distances in world units
+struct ShadowSettings {
+  near: f32,
+  depth_bias: f32,
+  slope_bias: f32,
+  normal_bias: f32
+}
+
 struct Light {
   pos: vec3<f32>,
+  range: f32,
   color: vec3<f32>,
+  intensity: f32,
+  shadow: ShadowSettings,
   view_proj: mat4x4<f32>
 }
 
+const MAX_LIGHTS: u32 = 16u;
+
+struct Lights {
+  count: u32,
+  lights: array<Light, MAX_LIGHTS>
+}
+
 @group(0)@binding(1)
-var<uniform> light:Light;
+var<uniform> lights: Lights;
+
+const MAX_CASCADES: u32 = 4u;
+
+struct Sun {
+  direction: vec3<f32>,
+  enabled: u32,
+  color: vec3<f32>,
+  intensity: f32,
+  cascade_count: u32,
+  blend: f32,
+  debug: u32,
+  _padding: u32,
+  shadow: ShadowSettings,
+  // far end of every cascade along the camera direction
+  splits: vec4<f32>,
+  view_projs: array<mat4x4<f32>, MAX_CASCADES>
+}
+
+@group(0)@binding(2)
+var<uniform> sun: Sun;
+
+const MAX_SPOT_LIGHTS: u32 = 8u;
+
+struct SpotLight {
+  pos: vec3<f32>,
+  range: f32,
+  color: vec3<f32>,
+  intensity: f32,
+  direction: vec3<f32>,
+  inner_angle: f32,
+  shadow: ShadowSettings,
+  outer_angle: f32,
+  view_proj: mat4x4<f32>
+}
+
+struct SpotLights {
+  count: u32,
+  lights: array<SpotLight, MAX_SPOT_LIGHTS>
+}
+
+@group(0)@binding(3)
+var<uniform> spot_lights: SpotLights;
+
+const SHADOW_FILTER_HARD: u32 = 0u;
+const SHADOW_FILTER_PCF: u32 = 1u;
+const SHADOW_FILTER_PCSS: u32 = 2u;
+
+struct ShadowParams {
+  filter_mode: u32,
+  // in shadow map texels
+  pcf_radius: f32,
+  // in world units
+  light_size: f32
+}
+
+@group(0)@binding(4)
+var<uniform> shadow_params: ShadowParams;
 
 
 @vertex
@@ -49,49 +127,439 @@ fn vs_main(v_in: VertexInput) -> VertexOutput {
     output.world_position = instance_matrix * vec4<f32>(v_in.position, 1.0);
     output.clip_position = camera.view_matrix * instance_matrix * vec4<f32>(v_in.position, 1.0);
     output.normal = normalize(instance_matrix * vec4<f32>(v_in.normal, 0.0)).xyz;
+    output.tangent = vec4<f32>((instance_matrix * vec4<f32>(v_in.tangent.xyz, 0.0)).xyz, v_in.tangent.w);
     return output;
 }
 
+// diffuse colour of Phong materials, base colour of PBR ones
 @group(1) @binding(0)
 var texture: texture_2d<f32>;
 @group(1) @binding(1)
 var texture_sampler: sampler;
+// metalness in blue
+@group(1) @binding(2)
+var metallic_texture: texture_2d<f32>;
+@group(1) @binding(3)
+var metallic_sampler: sampler;
+// roughness in green
+@group(1) @binding(4)
+var roughness_texture: texture_2d<f32>;
+@group(1) @binding(5)
+var roughness_sampler: sampler;
+@group(1) @binding(6)
+var emissive_texture: texture_2d<f32>;
+@group(1) @binding(7)
+var emissive_sampler: sampler;
+// ambient occlusion in red
+@group(1) @binding(8)
+var occlusion_texture: texture_2d<f32>;
+@group(1) @binding(9)
+var occlusion_sampler: sampler;
+// tangent space normal
+@group(1) @binding(10)
+var normal_texture: texture_2d<f32>;
+@group(1) @binding(11)
+var normal_sampler: sampler;
+// specular colour of Phong materials
+@group(1) @binding(12)
+var specular_texture: texture_2d<f32>;
+@group(1) @binding(13)
+var specular_sampler: sampler;
+// shininess of Phong materials in red
+@group(1) @binding(14)
+var shininess_texture: texture_2d<f32>;
+@group(1) @binding(15)
+var shininess_sampler: sampler;
+// opacity in red
+@group(1) @binding(16)
+var alpha_texture: texture_2d<f32>;
+@group(1) @binding(17)
+var alpha_sampler: sampler;
 
 struct Material {
   ambient_str: vec3<f32>,
   diffuse_str: vec3<f32>,
   specular_str: vec3<f32>,
-  shininess: f32
+  shininess: f32,
+  // PBR only
+  base_color: vec4<f32>,
+  emissive: vec3<f32>,
+  metallic: f32,
+  roughness: f32,
+  occlusion_strength: f32,
+  normal_scale: f32,
+  // Phong only, PBR uses the alpha of base_color
+  dissolve: f32,
+  // 0 for materials that are not cut out
+  alpha_cutoff: f32
 }
 
 @group(2)@binding(0)
 var<uniform> material:Material;
 
 @group(3) @binding(0)
-var shadow_texture: texture_depth_cube;
+var shadow_texture: texture_depth_cube_array;
 @group(3) @binding(1)
 var shadow_texture_sampler: sampler_comparison;
+@group(3) @binding(2)
+var sun_shadow_texture: texture_depth_2d_array;
+@group(3) @binding(3)
+var spot_shadow_texture: texture_depth_2d_array;
+// 1 where ambient light reaches unoccluded
+@group(3) @binding(4)
+var ambient_occlusion_texture: texture_2d<f32>;
 
+const POISSON_SAMPLES: u32 = 16u;
+// depth resolution of the PCSS blocker search
+const BLOCKER_STEPS: u32 = 4u;
+const POISSON_DISK = array<vec2<f32>, POISSON_SAMPLES>(
+    vec2<f32>(-0.94201624, -0.39906216),
+    vec2<f32>(0.94558609, -0.76890725),
+    vec2<f32>(-0.09418410, -0.92938870),
+    vec2<f32>(0.34495938, 0.29387760),
+    vec2<f32>(-0.91588581, 0.45771432),
+    vec2<f32>(-0.81544232, -0.87912464),
+    vec2<f32>(-0.38277543, 0.27676845),
+    vec2<f32>(0.97484398, 0.75648379),
+    vec2<f32>(0.44323325, -0.97511554),
+    vec2<f32>(0.53742981, -0.47373420),
+    vec2<f32>(-0.26496911, -0.41893023),
+    vec2<f32>(0.79197514, 0.19090188),
+    vec2<f32>(-0.24188840, 0.99706507),
+    vec2<f32>(-0.81409955, 0.91437590),
+    vec2<f32>(0.19984126, 0.78641367),
+    vec2<f32>(0.14383161, -0.14100790),
+);
 
+const CASCADE_COLORS = array<vec3<f32>, MAX_CASCADES>(
+    vec3<f32>(1.0, 0.3, 0.3),
+    vec3<f32>(0.3, 1.0, 0.3),
+    vec3<f32>(0.3, 0.3, 1.0),
+    vec3<f32>(1.0, 1.0, 0.3),
+);
 
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+const PI: f32 = 3.14159265;
+
+// what the lighting loop needs to know about the fragment's material
+struct Surface {
+  normal: vec3<f32>,
+  view_dir: vec3<f32>,
+  pbr: bool,
+  // Phong only
+  specular: vec3<f32>,
+  shininess: f32,
+  // PBR only
+  base_color: vec3<f32>,
+  metallic: f32,
+  roughness: f32,
+  // material occlusion, multiplied with the screen-space one
+  occlusion: f32
+}
+
+// Blinn-Phong terms shared by every light type, `shadow` darkens diffuse and specular
+fn blinn_phong(surface: Surface, light_dir: vec3<f32>, color: vec3<f32>, shadow: f32) -> vec3<f32> {
+    let half_dir = normalize(surface.view_dir + light_dir);
+
+    let diffuse = max(dot(surface.normal, light_dir), 0.0) * material.diffuse_str;
+    let specular = pow(max(dot(surface.normal, half_dir), 0.0), surface.shininess) * surface.specular;
+
+    return shadow * (diffuse + specular) * color;
+}
+
+// Cook-Torrance with the GGX distribution, Smith-Schlick geometry and Schlick Fresnel terms
+fn cook_torrance(surface: Surface, light_dir: vec3<f32>, color: vec3<f32>, shadow: f32) -> vec3<f32> {
+    let half_dir = normalize(surface.view_dir + light_dir);
+    let n_dot_l = max(dot(surface.normal, light_dir), 0.0);
+    let n_dot_v = max(dot(surface.normal, surface.view_dir), 1e-4);
+    let n_dot_h = max(dot(surface.normal, half_dir), 0.0);
+    let v_dot_h = max(dot(surface.view_dir, half_dir), 0.0);
+
+    // perceptual roughness, clamped so highlights of smooth surfaces do not vanish
+    let roughness = clamp(surface.roughness, 0.045, 1.0);
+    let alpha = roughness * roughness;
+    let alpha2 = alpha * alpha;
+    let d_denom = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    let distribution = alpha2 / (PI * d_denom * d_denom);
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let geometry = n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+    let f0 = mix(vec3<f32>(0.04), surface.base_color, surface.metallic);
+    let fresnel = f0 + (1.0 - f0) * pow(1.0 - v_dot_h, 5.0);
+
+    let specular = distribution * geometry * fresnel / (4.0 * n_dot_v * max(n_dot_l, 1e-4));
+    let diffuse = (1.0 - fresnel) * (1.0 - surface.metallic) * surface.base_color / PI;
+    // scaled by pi so a light lights a white diffuse surface as brightly as with Blinn-Phong
+    return shadow * (diffuse + specular) * PI * n_dot_l * color;
+}
 
-    let light_dir = normalize(light.pos - in.world_position.xyz);
-    let view_dir = normalize(camera.pos - in.world_position).xyz;
-    let half_dir = normalize(view_dir + light_dir);
-    let reflected = reflect(in.normal, half_dir);
+fn shade(surface: Surface, light_dir: vec3<f32>, color: vec3<f32>, shadow: f32) -> vec3<f32> {
+    if surface.pbr {
+        return cook_torrance(surface, light_dir, color, shadow);
+    }
+    return blinn_phong(surface, light_dir, color, shadow);
+}
+
+// constant ambient term lit by `light`, darkened by the material and screen-space `occlusion`
+fn ambient(surface: Surface, light: vec3<f32>, occlusion: f32) -> vec3<f32> {
+    if surface.pbr {
+        return 0.1 * light * surface.base_color * surface.occlusion * occlusion;
+    }
+    return 0.1 * light * material.ambient_str * occlusion;
+}
+
+// smooth window reaching zero at the light's range
+fn range_attenuation(distance: f32, range: f32) -> f32 {
+    let falloff = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    return falloff * falloff;
+}
+
+// per pixel angle the Poisson disk is rotated by, trades banding for noise
+fn interleaved_gradient_noise(frag_coord: vec2<f32>) -> f32 {
+    return fract(52.9829189 * fract(dot(frag_coord, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+// Poisson disk sample `k`, rotated and scaled to `radius`, spread perpendicular to the
+// cube map lookup direction `dir`
+fn disk_direction(dir: vec3<f32>, k: u32, rotation: mat2x2<f32>, radius: f32) -> vec3<f32> {
+    var up = vec3<f32>(0.0, 1.0, 0.0);
+    if abs(dir.y) > 0.99 {
+        up = vec3<f32>(0.0, 0.0, 1.0);
+    }
+    let tangent = normalize(cross(up, dir));
+    let bitangent = cross(dir, tangent);
+    var poisson_disk = POISSON_DISK;
+    let offset = rotation * poisson_disk[k] * radius;
+    return dir + tangent * offset.x + bitangent * offset.y;
+}
+
+fn pcf_cube(dir: vec3<f32>, i: u32, depth: f32, rotation: mat2x2<f32>, radius: f32) -> f32 {
+    var lit = 0.0;
+    for (var k = 0u; k < POISSON_SAMPLES; k++) {
+        let sample_dir = disk_direction(dir, k, rotation, radius);
+        lit += textureSampleCompareLevel(shadow_texture, shadow_texture_sampler, sample_dir, i, depth);
+    }
+    return lit / f32(POISSON_SAMPLES);
+}
+
+// world space distance a receiver is moved towards the light before comparing depths, growing
+// with the slope of the surface as seen from the light
+fn receiver_bias(shadow: ShadowSettings, normal: vec3<f32>, light_dir: vec3<f32>) -> f32 {
+    let cos_angle = clamp(dot(normal, light_dir), 0.05, 1.0);
+    let tan_angle = min(sqrt(1.0 - cos_angle * cos_angle) / cos_angle, 10.0);
+    return shadow.depth_bias + shadow.slope_bias * tan_angle;
+}
+
+// shadow of point light `i` on the fragment, filtered as `shadow_params` asks
+fn point_shadow(world_position: vec3<f32>, normal: vec3<f32>, i: u32, frag_coord: vec2<f32>) -> f32 {
+    let light = lights.lights[i];
+    let range = light.range;
+    let light_dir = normalize(light.pos - world_position);
+    let to_light = light.pos - (world_position + normal * light.shadow.normal_bias);
+    let distance = length(to_light);
+    let dir = -to_light / distance;
+    let biased_depth = (distance - receiver_bias(light.shadow, normal, light_dir)) / range;
+    if shadow_params.filter_mode == SHADOW_FILTER_HARD {
+        return textureSampleCompareLevel(shadow_texture, shadow_texture_sampler, dir, i, biased_depth);
+    }
+
+    let angle = 6.2831853 * interleaved_gradient_noise(frag_coord);
+    let rotation = mat2x2<f32>(cos(angle), sin(angle), -sin(angle), cos(angle));
+    // a cube face spans two units at unit distance from the light
+    let texel = 2.0 / f32(textureDimensions(shadow_texture).x);
+    if shadow_params.filter_mode == SHADOW_FILTER_PCF {
+        return pcf_cube(dir, i, biased_depth, rotation, shadow_params.pcf_radius * texel);
+    }
+
+    // PCSS: average the depth of the occluders within the light's size as seen from here.
+    // Depth textures can only be compared against, not read, on every backend, so the
+    // occluders are counted below a few depth steps and each step's new ones are placed
+    // halfway into it.
+    let search_radius = shadow_params.light_size / distance;
+    var blocker_depth = 0.0;
+    var blockers = 0.0;
+    for (var step = 1u; step <= BLOCKER_STEPS; step++) {
+        let step_depth = biased_depth * f32(step) / f32(BLOCKER_STEPS);
+        var occluded = 0.0;
+        for (var k = 0u; k < POISSON_SAMPLES; k++) {
+            let sample_dir = disk_direction(dir, k, rotation, search_radius);
+            occluded += 1.0 - textureSampleCompareLevel(shadow_texture, shadow_texture_sampler, sample_dir, i, step_depth);
+        }
+        let step_center = biased_depth * (f32(step) - 0.5) / f32(BLOCKER_STEPS);
+        blocker_depth += (occluded - blockers) * step_center;
+        blockers = occluded;
+    }
+    if blockers < 0.01 {
+        return 1.0;
+    }
+    // similar triangles between the light, the occluders and the receiver give the penumbra,
+    // dividing by the receiver distance turns it into a lookup offset
+    let blocker_distance = blocker_depth / blockers * range;
+    let penumbra = shadow_params.light_size * (distance - blocker_distance) / blocker_distance;
+    let radius = clamp(penumbra / distance, texel, search_radius);
+    return pcf_cube(dir, i, biased_depth, rotation, radius);
+}
+
+fn spot_shadow(world_position: vec3<f32>, normal: vec3<f32>, i: u32) -> f32 {
+    let light = spot_lights.lights[i];
+    let light_dir = normalize(light.pos - world_position);
+    let receiver = world_position + normal * light.shadow.normal_bias;
+    let clip = light.view_proj * vec4<f32>(receiver, 1.0);
+    let uv = clip.xy / clip.w * vec2<f32>(0.5, -0.5) + 0.5;
+    if clip.w <= 0.0 || any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+        return 1.0;
+    }
+    let distance = length(light.pos - receiver);
+    let biased_depth = (distance - receiver_bias(light.shadow, normal, light_dir)) / light.range;
+    return textureSampleCompareLevel(spot_shadow_texture, shadow_texture_sampler, uv, i, biased_depth);
+}
+
+fn sun_cascade_shadow(world_position: vec3<f32>, normal: vec3<f32>, cascade: u32) -> f32 {
+    let view_proj = sun.view_projs[cascade];
+    let receiver = world_position + normal * sun.shadow.normal_bias;
+    let clip = view_proj * vec4<f32>(receiver, 1.0);
+    let uv = clip.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || clip.z > 1.0 {
+        return 1.0;
+    }
+    // orthographic depth is linear, this scales world distances into it
+    let depth_scale = length(vec3<f32>(view_proj[0].z, view_proj[1].z, view_proj[2].z));
+    let bias = receiver_bias(sun.shadow, normal, -normalize(sun.direction)) * depth_scale;
+    return textureSampleCompareLevel(sun_shadow_texture, shadow_texture_sampler, uv, cascade, clip.z - bias);
+}
+
+// shadow of the sun from the cascade the fragment falls into, faded into the next cascade
+// towards its far end and into no shadow at the end of the last one
+fn sun_shadow(world_position: vec3<f32>, normal: vec3<f32>, cascade: u32) -> f32 {
+    let depth = dot(world_position - camera.pos.xyz, camera.direction.xyz);
+    let far = sun.splits[cascade];
+    var near = 0.0;
+    if cascade > 0u {
+        near = sun.splits[cascade - 1u];
+    }
+    let shadow = sun_cascade_shadow(world_position, normal, cascade);
+    let blend = clamp((far - depth) / max((far - near) * sun.blend, 0.0001), 0.0, 1.0);
+    if blend >= 1.0 {
+        return shadow;
+    }
+    var next = 1.0;
+    if cascade + 1u < sun.cascade_count {
+        next = sun_cascade_shadow(world_position, normal, cascade + 1u);
+    }
+    return mix(next, shadow, blend);
+}
+
+// interpolated normal bent by the normal map, in the tangent frame of the vertices
+fn mapped_normal(in: VertexOutput) -> vec3<f32> {
+    let normal = normalize(in.normal);
+    // meshes without texture coordinates have no tangents
+    if dot(in.tangent.xyz, in.tangent.xyz) < 1e-6 {
+        return normal;
+    }
+    let tangent = normalize(in.tangent.xyz - normal * dot(normal, in.tangent.xyz));
+    let bitangent = cross(normal, tangent) * in.tangent.w;
+    var tangent_normal = textureSample(normal_texture, normal_sampler, in.tex_cords).xyz * 2.0 - 1.0;
+    tangent_normal = vec3<f32>(tangent_normal.xy * material.normal_scale, tangent_normal.z);
+    return normalize(mat3x3<f32>(tangent, bitangent, normal) * tangent_normal);
+}
+
+// light reaching the fragment from every light, `occlusion` is the screen-space ambient occlusion.
+// Ambient is added once, lit by the brightest light reaching the fragment, so it neither grows
+// with the number of lights nor comes from lights out of range.
+fn direct_lighting(in: VertexOutput, surface: Surface, occlusion: f32) -> vec3<f32> {
+    let normal = normalize(in.normal);
+    var color = vec3<f32>(0.0);
+    var ambient_light = vec3<f32>(0.0);
+    for (var i = 0u; i < min(lights.count, MAX_LIGHTS); i++) {
+        let light = lights.lights[i];
+        let to_light = light.pos - in.world_position.xyz;
+        let distance = length(to_light);
+
+        let attenuation = range_attenuation(distance, light.range);
+        let shadow = point_shadow(in.world_position.xyz, normal, i, in.clip_position.xy);
 
-    let ambient = 0.1 * light.color * material.ambient_str;
-    let diffuse = max(dot(in.normal, light_dir), 0.0) * material.diffuse_str;
-    let specular = pow(max(dot(in.normal, half_dir), 0.0), material.shininess) * material.specular_str;
+        color += shade(surface, to_light / distance, light.color, attenuation * shadow) * light.intensity;
+        ambient_light = max(ambient_light, light.color * light.intensity * attenuation);
+    }
 
-    let l = in.world_position.xyz - light.pos;
-    let biased_depth = length(l) / 100.0 - 0.005;
-    let shadow = textureSampleCompare(shadow_texture, shadow_texture_sampler, l, biased_depth);
+    for (var i = 0u; i < min(spot_lights.count, MAX_SPOT_LIGHTS); i++) {
+        let light = spot_lights.lights[i];
+        let to_light = light.pos - in.world_position.xyz;
+        let distance = length(to_light);
+        let light_dir = to_light / distance;
 
-    let color = (ambient + shadow * (diffuse + specular)) * light.color;
+        // full strength inside the inner cone, fading out smoothly towards the outer one
+        let cos_angle = dot(-light_dir, normalize(light.direction));
+        let cone = smoothstep(cos(light.outer_angle), cos(light.inner_angle), cos_angle);
+        let attenuation = cone * range_attenuation(distance, light.range);
+        let shadow = spot_shadow(in.world_position.xyz, normal, i);
 
-    return vec4<f32>(color, 1.0) * textureSample(texture, texture_sampler, in.tex_cords);
-    //return vec4<f32>(shadow,shadow,shadow, 1.0);
+        color += shade(surface, light_dir, light.color, attenuation * shadow) * light.intensity;
+        ambient_light = max(ambient_light, light.color * light.intensity * attenuation);
+    }
+
+    if sun.enabled != 0u {
+        ambient_light = max(ambient_light, sun.color * sun.intensity);
+    }
+    color += ambient(surface, ambient_light, occlusion);
+
+    if sun.enabled != 0u {
+        let depth = dot(in.world_position.xyz - camera.pos.xyz, camera.direction.xyz);
+        var cascade = 0u;
+        while cascade < sun.cascade_count && depth > sun.splits[cascade] {
+            cascade++;
+        }
+        var shadow = 1.0;
+        if cascade < sun.cascade_count {
+            shadow = sun_shadow(in.world_position.xyz, normal, cascade);
+        }
+        var sun_color = shade(surface, -normalize(sun.direction), sun.color, shadow) * sun.intensity;
+        if sun.debug != 0u && cascade < sun.cascade_count {
+            var cascade_colors = CASCADE_COLORS;
+            color *= cascade_colors[cascade];
+            sun_color *= cascade_colors[cascade];
+        }
+        color += sun_color;
+    }
+    return color;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let occlusion = textureLoad(ambient_occlusion_texture, vec2<i32>(in.clip_position.xy), 0).r;
+    let diffuse = textureSample(texture, texture_sampler, in.tex_cords);
+    let alpha = material.dissolve * textureSample(alpha_texture, alpha_sampler, in.tex_cords).r * diffuse.a;
+    if alpha < material.alpha_cutoff {
+        discard;
+    }
+    var surface: Surface;
+    surface.normal = mapped_normal(in);
+    surface.view_dir = normalize(camera.pos - in.world_position).xyz;
+    surface.pbr = false;
+    surface.specular = material.specular_str * textureSample(specular_texture, specular_sampler, in.tex_cords).rgb;
+    // pow(0, 0) is undefined
+    surface.shininess = max(material.shininess * textureSample(shininess_texture, shininess_sampler, in.tex_cords).r, 1e-4);
+    let emissive = material.emissive * textureSample(emissive_texture, emissive_sampler, in.tex_cords).rgb;
+    let color = direct_lighting(in, surface, occlusion) * diffuse.rgb + emissive;
+    return vec4<f32>(color, alpha);
+}
+
+@fragment
+fn fs_pbr(in: VertexOutput) -> @location(0) vec4<f32> {
+    let occlusion = textureLoad(ambient_occlusion_texture, vec2<i32>(in.clip_position.xy), 0).r;
+    let base_color = material.base_color * textureSample(texture, texture_sampler, in.tex_cords);
+    let alpha = base_color.a * textureSample(alpha_texture, alpha_sampler, in.tex_cords).r;
+    if alpha < material.alpha_cutoff {
+        discard;
+    }
+    let material_occlusion = textureSample(occlusion_texture, occlusion_sampler, in.tex_cords).r;
+    var surface: Surface;
+    surface.normal = mapped_normal(in);
+    surface.view_dir = normalize(camera.pos - in.world_position).xyz;
+    surface.pbr = true;
+    surface.base_color = base_color.rgb;
+    surface.metallic = clamp(material.metallic * textureSample(metallic_texture, metallic_sampler, in.tex_cords).b, 0.0, 1.0);
+    surface.roughness = material.roughness * textureSample(roughness_texture, roughness_sampler, in.tex_cords).g;
+    surface.occlusion = mix(1.0, material_occlusion, material.occlusion_strength);
+    let emissive = material.emissive * textureSample(emissive_texture, emissive_sampler, in.tex_cords).rgb;
+    let color = direct_lighting(in, surface, occlusion) + emissive;
+    return vec4<f32>(color, alpha);
 }
@@ -0,0 +1,51 @@
+struct VertexInput {
+  @location(0) position: vec3<f32>,
+  @location(1) tex_cords: vec2<f32>,
+  @location(2) normal: vec3<f32>,
+  @location(3) instance_transform_0: vec4<f32>,
+  @location(4) instance_transform_1: vec4<f32>,
+  @location(5) instance_transform_2: vec4<f32>,
+  @location(6) instance_transform_3: vec4<f32>,
+}
+
+
+struct VertexOutput {
+  @builtin(position) clip_position: vec4<f32>,
+  @location(0) normal: vec3<f32>,
+  @location(1) world_position: vec3<f32>
+}
+
+struct Camera {
+  view_matrix: mat4x4<f32>,
+  pos: vec4<f32>,
+  direction: vec4<f32>
+}
+
+@group(0)
+@binding(0)
+var<uniform> camera: Camera;
+
+
+@vertex
+fn vs_main(v_in: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    let instance_matrix = mat4x4<f32>(
+        v_in.instance_transform_0,
+        v_in.instance_transform_1,
+        v_in.instance_transform_2,
+        v_in.instance_transform_3,
+    );
+    let world_position = instance_matrix * vec4<f32>(v_in.position, 1.0);
+    output.world_position = world_position.xyz;
+    output.clip_position = camera.view_matrix * world_position;
+    output.normal = normalize(instance_matrix * vec4<f32>(v_in.normal, 0.0)).xyz;
+    return output;
+}
+
+
+// world space normal and the distance along the camera direction, what the SSAO pass reads
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let depth = dot(in.world_position - camera.pos.xyz, camera.direction.xyz);
+    return vec4<f32>(normalize(in.normal), depth);
+}
@@ -11,19 +11,58 @@ struct VertexInput {
 
 struct VertexOutput {
   @builtin(position) clip_position: vec4<f32>,
-  @location(0) world_position: vec4<f32>
+  @location(0) world_position: vec4<f32>,
+  @location(1) tex_cords: vec2<f32>
 }
 
 
+struct ShadowSettings {
+  near: f32,
+  depth_bias: f32,
+  slope_bias: f32,
+  normal_bias: f32
+}
+
 struct Light {
   pos: vec3<f32>,
+  range: f32,
   color: vec3<f32>,
+  intensity: f32,
+  shadow: ShadowSettings,
   view_proj: mat4x4<f32>,
 }
 
 @group(0)@binding(0)
 var<uniform> light:Light;
 
+// the material bindings of main.wgsl that decide the alpha of cutout materials
+@group(1) @binding(0)
+var texture: texture_2d<f32>;
+@group(1) @binding(1)
+var texture_sampler: sampler;
+@group(1) @binding(16)
+var alpha_texture: texture_2d<f32>;
+@group(1) @binding(17)
+var alpha_sampler: sampler;
+
+struct Material {
+  ambient_str: vec3<f32>,
+  diffuse_str: vec3<f32>,
+  specular_str: vec3<f32>,
+  shininess: f32,
+  base_color: vec4<f32>,
+  emissive: vec3<f32>,
+  metallic: f32,
+  roughness: f32,
+  occlusion_strength: f32,
+  normal_scale: f32,
+  dissolve: f32,
+  alpha_cutoff: f32
+}
+
+@group(2)@binding(0)
+var<uniform> material:Material;
+
 
 @vertex
 fn vs_main(v_in: VertexInput) -> VertexOutput {
@@ -36,13 +75,39 @@ fn vs_main(v_in: VertexInput) -> VertexOutput {
     );
     output.world_position = instance_matrix * vec4<f32>(v_in.position, 1.0);
     output.clip_position = light.view_proj * instance_matrix * vec4<f32>(v_in.position, 1.0);
+    output.tex_cords = v_in.tex_cords;
     return output;
 }
 
 
+fn light_depth(in: VertexOutput) -> f32 {
+    let depth = length(in.world_position.xyz - light.pos);
+    return depth / light.range;
+}
+
+// discards what the main pass discards, only one of dissolve and base_color.a is set, the
+// other stays 1
+fn cutout(tex_cords: vec2<f32>) {
+    let alpha = material.dissolve * material.base_color.a * textureSample(texture, texture_sampler, tex_cords).a * textureSample(alpha_texture, alpha_sampler, tex_cords).r;
+    if alpha < material.alpha_cutoff {
+        discard;
+    }
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @builtin(frag_depth) f32 {
-    let depth = length(in.world_position.xyz - light.pos);
-    return depth/100.0;
+    return light_depth(in);
+}
+
+@fragment
+fn fs_cutout(in: VertexOutput) -> @builtin(frag_depth) f32 {
+    cutout(in.tex_cords);
+    return light_depth(in);
+}
+
+// cascades keep the rasterized depth
+@fragment
+fn fs_sun_cutout(in: VertexOutput) {
+    cutout(in.tex_cords);
 }
 
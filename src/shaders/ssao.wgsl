@@ -0,0 +1,129 @@
+
+const MAX_SAMPLES: u32 = 32u;
+// size of the tiled noise texture, the blur averages over the same area
+const NOISE_SIZE: i32 = 4;
+
+struct Params {
+  view_proj: mat4x4<f32>,
+  inverse_view_proj: mat4x4<f32>,
+  camera_position: vec4<f32>,
+  camera_direction: vec4<f32>,
+  // in world units
+  radius: f32,
+  bias: f32,
+  // exponent applied to the final term
+  intensity: f32,
+  sample_count: u32,
+  // hemisphere around +z, scaled to the unit sphere
+  kernel: array<vec4<f32>, MAX_SAMPLES>
+}
+
+// world normal in rgb, distance along the camera direction in a
+@group(0) @binding(0)
+var normal_depth_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var noise_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var<uniform> params: Params;
+// read by the blur and the debug view
+@group(0) @binding(3)
+var occlusion_texture: texture_2d<f32>;
+
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+
+fn load_normal_depth(pixel: vec2<i32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(normal_depth_texture));
+    return textureLoad(normal_depth_texture, clamp(pixel, vec2<i32>(0), size - 1), 0);
+}
+
+fn depth_along_view(position: vec3<f32>) -> f32 {
+    return dot(position - params.camera_position.xyz, params.camera_direction.xyz);
+}
+
+// position of the surface under a pixel, from the ray through it and its stored depth
+fn world_position(pixel: vec2<f32>, depth: f32) -> vec3<f32> {
+    let size = vec2<f32>(textureDimensions(normal_depth_texture));
+    let ndc = vec2<f32>(pixel.x / size.x * 2.0 - 1.0, 1.0 - pixel.y / size.y * 2.0);
+    let far = params.inverse_view_proj * vec4<f32>(ndc, 1.0, 1.0);
+    let ray = normalize(far.xyz / far.w - params.camera_position.xyz);
+    return params.camera_position.xyz + ray * depth / dot(ray, params.camera_direction.xyz);
+}
+
+@fragment
+fn fs_ssao(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    let normal_depth = load_normal_depth(pixel);
+    let depth = normal_depth.a;
+    let normal = normal_depth.rgb;
+    // background, nothing to occlude
+    if dot(normal, normal) < 0.5 {
+        return vec4<f32>(1.0);
+    }
+    let origin = world_position(position.xy, depth);
+
+    // kernel rotated around the normal by the tiled noise, turning banding into noise the
+    // blur removes
+    var noise = textureLoad(noise_texture, pixel % NOISE_SIZE, 0).xyz * 2.0 - 1.0;
+    if abs(dot(normalize(noise), normal)) > 0.99 {
+        noise = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 0.0, 1.0), abs(normal.x) > 0.9);
+    }
+    let tangent = normalize(noise - normal * dot(noise, normal));
+    let bitangent = cross(normal, tangent);
+    let tbn = mat3x3<f32>(tangent, bitangent, normal);
+
+    let size = vec2<f32>(textureDimensions(normal_depth_texture));
+    let count = min(params.sample_count, MAX_SAMPLES);
+    var occlusion = 0.0;
+    for (var i = 0u; i < count; i++) {
+        let sample_position = origin + tbn * params.kernel[i].xyz * params.radius;
+        let clip = params.view_proj * vec4<f32>(sample_position, 1.0);
+        let ndc = clip.xy / clip.w;
+        let sample_pixel = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5) * size;
+        let scene = load_normal_depth(vec2<i32>(sample_pixel));
+        let scene_depth = select(1e9, scene.a, dot(scene.rgb, scene.rgb) > 0.5);
+        // occluders much further away than the radius belong to another object
+        let range_check = smoothstep(0.0, 1.0, params.radius / max(abs(depth - scene_depth), 1e-4));
+        if scene_depth < depth_along_view(sample_position) - params.bias {
+            occlusion += range_check;
+        }
+    }
+    let ambient = pow(1.0 - occlusion / f32(max(count, 1u)), params.intensity);
+    return vec4<f32>(ambient, 0.0, 0.0, 1.0);
+}
+
+// averages the noise tile, skipping pixels across depth and normal discontinuities so the
+// occlusion does not bleed over object edges
+@fragment
+fn fs_blur(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    let center = load_normal_depth(pixel);
+    let size = vec2<i32>(textureDimensions(occlusion_texture));
+    var total = 0.0;
+    var weights = 0.0;
+    for (var y = -NOISE_SIZE / 2; y < NOISE_SIZE / 2; y++) {
+        for (var x = -NOISE_SIZE / 2; x < NOISE_SIZE / 2; x++) {
+            let tap = clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            let other = load_normal_depth(tap);
+            let depth_weight = exp(-abs(other.a - center.a) / (0.05 * max(center.a, 0.1)));
+            let normal_weight = pow(max(dot(other.rgb, center.rgb), 0.0), 8.0);
+            let weight = max(depth_weight * normal_weight, 1e-3);
+            total += textureLoad(occlusion_texture, tap, 0).r * weight;
+            weights += weight;
+        }
+    }
+    return vec4<f32>(total / weights, 0.0, 0.0, 1.0);
+}
+
+// the output is sRGB, decode so the stored value is the occlusion itself
+@fragment
+fn fs_debug(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let occlusion = textureLoad(occlusion_texture, vec2<i32>(position.xy), 0).r;
+    let linear = select(pow((occlusion + 0.055) / 1.055, 2.4), occlusion / 12.92, occlusion <= 0.04045);
+    return vec4<f32>(vec3<f32>(linear), 1.0);
+}
@@ -0,0 +1,89 @@
+
+const TONEMAP_LINEAR: u32 = 0u;
+const TONEMAP_REINHARD: u32 = 1u;
+const TONEMAP_ACES: u32 = 2u;
+const TONEMAP_AGX: u32 = 3u;
+
+struct Params {
+  tone_mapper: u32,
+  exposure: f32,
+  auto_exposure: u32
+}
+
+// written by the auto exposure compute pass
+struct AutoExposure {
+  average_luminance: f32,
+  exposure: f32
+}
+
+@group(0) @binding(0)
+var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> params: Params;
+@group(0) @binding(2)
+var<uniform> auto_exposure: AutoExposure;
+
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// minimal AgX after Benjamin Wrensch, polynomial fit of the default contrast curve
+fn agx_contrast(x: vec3<f32>) -> vec3<f32> {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn agx(color: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3<f32>(
+        vec3<f32>(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+        vec3<f32>(0.0784335999999992, 0.878468636469772, 0.0784336),
+        vec3<f32>(0.0792237451477643, 0.0791661274605434, 0.879142973793104),
+    );
+    let outset = mat3x3<f32>(
+        vec3<f32>(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+        vec3<f32>(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+        vec3<f32>(-0.0990297440797205, -0.0989611768448433, 1.15107367264116),
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+    let encoded = clamp((log2(max(inset * color, vec3<f32>(1e-10))) - min_ev) / (max_ev - min_ev), vec3<f32>(0.0), vec3<f32>(1.0));
+    let curved = outset * agx_contrast(encoded);
+    // the curve produces display encoded values, the target does the sRGB encoding itself
+    return pow(max(curved, vec3<f32>(0.0)), vec3<f32>(2.2));
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    var exposure = params.exposure;
+    if params.auto_exposure != 0u {
+        exposure *= auto_exposure.exposure;
+    }
+    let color = textureLoad(hdr_texture, vec2<i32>(position.xy), 0).rgb * exposure;
+    switch params.tone_mapper {
+        case TONEMAP_REINHARD: {
+            return vec4<f32>(reinhard(color), 1.0);
+        }
+        case TONEMAP_ACES: {
+            return vec4<f32>(aces(color), 1.0);
+        }
+        case TONEMAP_AGX: {
+            return vec4<f32>(agx(color), 1.0);
+        }
+        default: {
+            return vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+        }
+    }
+}
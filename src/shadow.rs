@@ -0,0 +1,433 @@
+use winit::dpi::PhysicalSize;
+
+use crate::binding::{Binding, TextureBinding};
+use crate::camera::Camera;
+use crate::layouts::Layouts;
+use crate::light::{DirectionalLight, Light, ShadowSettings, SpotLight, SunRaw, MAX_CASCADES};
+use crate::texture::Texture;
+
+/// Shadow cube maps of the point lights, light `i` renders into cube `i` of a cube map array.
+pub struct PointShadows {
+    pub cube_maps: Texture,
+    /// Depth view and light uniform of every cube face, six per light in
+    /// +X, -X, +Y, -Y, +Z, -Z order.
+    pub faces: Vec<(wgpu::TextureView, Binding)>,
+    /// Number of lights the cube map array has room for.
+    pub capacity: usize,
+}
+
+impl PointShadows {
+    pub fn new(device: &wgpu::Device, layouts: &Layouts, size: u32, capacity: usize) -> Self {
+        // GL picks the texture type from the layer count, six layers would make a plain cube map
+        // that cannot be viewed as a cube map array
+        let capacity = capacity.max(2);
+        let cube_maps = Texture::create_cube_array_depth_texture(
+            device,
+            PhysicalSize::new(size, size),
+            capacity as u32,
+        );
+        let faces = (0..6 * capacity as u32)
+            .map(|layer| {
+                (
+                    layer_view(&cube_maps, layer),
+                    light_uniform(device, layouts),
+                )
+            })
+            .collect();
+        return PointShadows {
+            cube_maps,
+            faces,
+            capacity,
+        };
+    }
+}
+
+/// Shadow maps of the spot lights, light `i` renders into layer `i` of a texture array.
+pub struct SpotShadows {
+    pub shadow_maps: Texture,
+    /// Depth view and light uniform of every layer.
+    pub layers: Vec<(wgpu::TextureView, Binding)>,
+    /// Number of lights the texture array has room for.
+    pub capacity: usize,
+}
+
+impl SpotShadows {
+    pub fn new(device: &wgpu::Device, layouts: &Layouts, size: u32, capacity: usize) -> Self {
+        // a single layer would make GL create a plain 2D texture instead of an array
+        let capacity = capacity.max(2);
+        let shadow_maps = Texture::create_array_depth_texture(
+            device,
+            PhysicalSize::new(size, size),
+            capacity as u32,
+        );
+        let layers = (0..capacity as u32)
+            .map(|layer| {
+                (
+                    layer_view(&shadow_maps, layer),
+                    light_uniform(device, layouts),
+                )
+            })
+            .collect();
+        return SpotShadows {
+            shadow_maps,
+            layers,
+            capacity,
+        };
+    }
+}
+
+/// How the point light shadow cube maps are filtered.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ShadowFilter {
+    /// A single comparison, hard and aliased edges.
+    #[default]
+    Hard,
+    /// Percentage closer filtering over a fixed Poisson disk, rotated per pixel.
+    Pcf,
+    /// Percentage closer soft shadows, the disk grows with the distance between blocker and
+    /// receiver so contact shadows stay sharp.
+    Pcss,
+}
+
+impl ShadowFilter {
+    /// The next mode, wrapping around, for cycling through them at runtime.
+    pub fn next(self) -> ShadowFilter {
+        return match self {
+            ShadowFilter::Hard => ShadowFilter::Pcf,
+            ShadowFilter::Pcf => ShadowFilter::Pcss,
+            ShadowFilter::Pcss => ShadowFilter::Hard,
+        };
+    }
+}
+
+/// Shadow filtering settings, they can be changed between frames.
+#[derive(Copy, Clone, Debug)]
+pub struct ShadowConfig {
+    pub filter: ShadowFilter,
+    /// Radius of the PCF disk in shadow map texels.
+    pub pcf_radius: f32,
+    /// Radius of the lights in world units as seen by PCSS, bigger lights give softer shadows.
+    pub light_size: f32,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        return ShadowConfig {
+            filter: ShadowFilter::Hard,
+            pcf_radius: 1.5,
+            light_size: 0.1,
+        };
+    }
+}
+
+/// Uniform layout of `ShadowConfig` in `Globals`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowParamsRaw {
+    /// 0 hard, 1 PCF, 2 PCSS.
+    pub filter_mode: u32,
+    pub pcf_radius: f32,
+    pub light_size: f32,
+    pub _padding: u32,
+}
+
+impl ShadowParamsRaw {
+    pub fn new(config: &ShadowConfig) -> ShadowParamsRaw {
+        return ShadowParamsRaw {
+            filter_mode: config.filter as u32,
+            pcf_radius: config.pcf_radius,
+            light_size: config.light_size,
+            _padding: 0,
+        };
+    }
+}
+
+/// Settings of the directional light's cascaded shadow maps, they can be changed between frames.
+#[derive(Copy, Clone, Debug)]
+pub struct CascadeConfig {
+    /// Number of cascades the view frustum is split into, 1 to `MAX_CASCADES`.
+    pub count: u32,
+    /// Split distribution, 0 splits the shadow distance evenly and 1 logarithmically.
+    pub split_lambda: f32,
+    /// Distance from the camera the cascades reach, nothing further away is shadowed.
+    pub max_distance: f32,
+    /// Fraction of every cascade, at its far end, blended into the next one.
+    pub blend: f32,
+    /// Tint every cascade in its own colour.
+    pub debug: bool,
+}
+
+impl Default for CascadeConfig {
+    fn default() -> Self {
+        return CascadeConfig {
+            count: 4,
+            split_lambda: 0.5,
+            max_distance: 50.0,
+            blend: 0.1,
+            debug: false,
+        };
+    }
+}
+
+/// Shadow maps of the directional light, cascade `i` renders into layer `i` of a texture array.
+pub struct SunShadows {
+    pub shadow_maps: Texture,
+    /// Depth view and light uniform of every cascade.
+    pub cascades: Vec<(wgpu::TextureView, Binding)>,
+}
+
+impl SunShadows {
+    pub fn new(device: &wgpu::Device, layouts: &Layouts, size: u32) -> Self {
+        // always allocated for every cascade, a single layer would not be an array on GL either
+        let shadow_maps = Texture::create_array_depth_texture(
+            device,
+            PhysicalSize::new(size, size),
+            MAX_CASCADES as u32,
+        );
+        let cascades = (0..MAX_CASCADES as u32)
+            .map(|layer| {
+                (
+                    layer_view(&shadow_maps, layer),
+                    light_uniform(device, layouts),
+                )
+            })
+            .collect();
+        return SunShadows {
+            shadow_maps,
+            cascades,
+        };
+    }
+}
+
+/// Bind group of the point light cube maps, the directional light cascades and the spot light
+/// shadow maps, along with the screen's ambient occlusion the main pass shades with.
+pub fn shadow_binding(
+    device: &wgpu::Device,
+    layouts: &Layouts,
+    point_shadows: &PointShadows,
+    sun_shadows: &SunShadows,
+    spot_shadows: &SpotShadows,
+    ambient_occlusion: &Texture,
+) -> TextureBinding {
+    return TextureBinding {
+        bind_group: device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layouts.shadow_texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &point_shadows.cube_maps.texture_view,
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&point_shadows.cube_maps.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &sun_shadows.shadow_maps.texture_view,
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(
+                        &spot_shadows.shadow_maps.texture_view,
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&ambient_occlusion.texture_view),
+                },
+            ],
+        }),
+        bind_index: 3,
+    };
+}
+
+fn layer_view(texture: &Texture, layer: u32) -> wgpu::TextureView {
+    return texture.texture.create_view(&wgpu::TextureViewDescriptor {
+        base_array_layer: layer,
+        array_layer_count: Some(1),
+        format: Some(wgpu::TextureFormat::Depth32Float),
+        dimension: Some(wgpu::TextureViewDimension::D2),
+        aspect: wgpu::TextureAspect::DepthOnly,
+        ..Default::default()
+    });
+}
+
+fn light_uniform(device: &wgpu::Device, layouts: &Layouts) -> Binding {
+    return Binding::create_binding(
+        device,
+        &layouts.light_bind_group_layout,
+        vec![bytemuck::cast_slice(&[Light::new([0.0; 3], [0.0; 3])])],
+        0,
+    );
+}
+
+/// View-projection matrices of the six cube faces of a point light, in layer order.
+pub fn cube_face_view_projs(light: &Light) -> [glam::Mat4; 6] {
+    let pos = glam::Vec3::from_array(light.pos);
+    let perspective =
+        glam::Mat4::perspective_lh(f32::to_radians(90.0), 1.0, light.shadow.near, light.range);
+    return [
+        (glam::Vec3::X, glam::Vec3::Y),
+        (-glam::Vec3::X, glam::Vec3::Y),
+        (glam::Vec3::Y, -glam::Vec3::Z),
+        (-glam::Vec3::Y, glam::Vec3::Z),
+        (glam::Vec3::Z, glam::Vec3::Y),
+        (-glam::Vec3::Z, glam::Vec3::Y),
+    ]
+    .map(|(forward, up)| perspective * glam::Mat4::look_at_lh(pos, pos + forward, up));
+}
+
+/// View-projection of a spot light, a perspective covering its whole cone.
+pub fn spot_view_proj(light: &SpotLight) -> glam::Mat4 {
+    let pos = glam::Vec3::from_array(light.pos);
+    let direction = glam::Vec3::from_array(light.direction).normalize();
+    let up = match direction.y.abs() > 0.99 {
+        true => glam::Vec3::Z,
+        false => glam::Vec3::Y,
+    };
+    let fov = (2.0 * light.outer_angle).clamp(f32::to_radians(1.0), f32::to_radians(170.0));
+    let perspective = glam::Mat4::perspective_lh(fov, 1.0, light.shadow.near, light.range);
+    return perspective * glam::Mat4::look_at_lh(pos, pos + direction, up);
+}
+
+/// Far end of every cascade between `near` and `far`, mixing logarithmic and even splits by
+/// `lambda`. Entries past `count` repeat `far`.
+pub fn cascade_splits(near: f32, far: f32, count: u32, lambda: f32) -> [f32; MAX_CASCADES] {
+    let count = count.clamp(1, MAX_CASCADES as u32);
+    let mut splits = [far; MAX_CASCADES];
+    for (i, split) in splits.iter_mut().enumerate().take(count as usize) {
+        let fraction = (i + 1) as f32 / count as f32;
+        let logarithmic = near * (far / near).powf(fraction);
+        let uniform = near + (far - near) * fraction;
+        *split = lambda * logarithmic + (1.0 - lambda) * uniform;
+    }
+    return splits;
+}
+
+/// How far behind a cascade, towards the light, shadow casters are still rendered.
+const CASTER_DISTANCE: f32 = 50.0;
+
+/// Orthographic view-projection of the light covering the part of the camera frustum between
+/// `near` and `far`. It is fit around the slice's bounding sphere and snapped to whole shadow
+/// map texels, so it neither changes size nor shimmers as the camera turns and moves.
+pub fn cascade_view_proj(
+    camera: &Camera,
+    near: f32,
+    far: f32,
+    direction: glam::Vec3,
+    shadow_map_size: u32,
+) -> glam::Mat4 {
+    let view = glam::Mat4::look_at_rh(
+        camera.position,
+        camera.position + camera.direction,
+        glam::Vec3::Y,
+    );
+    let projection = glam::Mat4::perspective_rh(camera.fov, camera.aspect_ratio, near, far);
+    let inverse = (projection * view).inverse();
+    let corners = [
+        (-1.0, -1.0, 0.0),
+        (1.0, -1.0, 0.0),
+        (-1.0, 1.0, 0.0),
+        (1.0, 1.0, 0.0),
+        (-1.0, -1.0, 1.0),
+        (1.0, -1.0, 1.0),
+        (-1.0, 1.0, 1.0),
+        (1.0, 1.0, 1.0),
+    ]
+    .map(|(x, y, z)| inverse.project_point3(glam::vec3(x, y, z)));
+    let center = corners.iter().sum::<glam::Vec3>() / corners.len() as f32;
+    let radius = corners
+        .iter()
+        .map(|corner| corner.distance(center))
+        .fold(0.0, f32::max);
+    // round so floating point noise does not change the texel size from frame to frame
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let direction = direction.normalize();
+    let up = match direction.y.abs() > 0.99 {
+        true => glam::Vec3::Z,
+        false => glam::Vec3::Y,
+    };
+    let eye = center - direction * (radius + CASTER_DISTANCE);
+    let light_view = glam::Mat4::look_at_lh(eye, center, up);
+    let mut light_projection = glam::Mat4::orthographic_lh(
+        -radius,
+        radius,
+        -radius,
+        radius,
+        0.0,
+        2.0 * radius + CASTER_DISTANCE,
+    );
+
+    let texels = shadow_map_size as f32 / 2.0;
+    let origin = (light_projection * light_view).transform_point3(glam::Vec3::ZERO) * texels;
+    let offset = (origin.round() - origin) / texels;
+    light_projection.w_axis.x += offset.x;
+    light_projection.w_axis.y += offset.y;
+    return light_projection * light_view;
+}
+
+/// Uniform of the directional light, with its cascades fit to the camera.
+pub fn sun_raw(
+    sun: Option<&DirectionalLight>,
+    camera: &Camera,
+    near: f32,
+    config: &CascadeConfig,
+    shadow_map_size: u32,
+) -> SunRaw {
+    let count = config.count.clamp(1, MAX_CASCADES as u32);
+    let splits = cascade_splits(near, config.max_distance, count, config.split_lambda);
+    let mut raw = SunRaw {
+        direction: [0.0, -1.0, 0.0],
+        enabled: 0,
+        color: [0.0; 3],
+        intensity: 0.0,
+        cascade_count: count,
+        blend: config.blend,
+        debug: config.debug as u32,
+        shadow: ShadowSettings::default(),
+        _padding: 0,
+        splits,
+        view_projs: [[[0.0; 4]; 4]; MAX_CASCADES],
+    };
+    let Some(sun) = sun else {
+        return raw;
+    };
+    raw.direction = sun.direction;
+    raw.enabled = 1;
+    raw.color = sun.color;
+    raw.intensity = sun.intensity;
+    raw.shadow = sun.shadow;
+    let mut cascade_near = near;
+    for (view_proj, far) in raw.view_projs.iter_mut().zip(splits).take(count as usize) {
+        *view_proj = cascade_view_proj(
+            camera,
+            cascade_near,
+            far,
+            glam::Vec3::from_array(sun.direction),
+            shadow_map_size,
+        )
+        .to_cols_array_2d();
+        cascade_near = far;
+    }
+    return raw;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cascade_splits_follow_lambda() {
+        let uniform = cascade_splits(1.0, 100.0, 4, 0.0);
+        assert_eq!(uniform, [25.75, 50.5, 75.25, 100.0]);
+        let logarithmic = cascade_splits(1.0, 100.0, 2, 1.0);
+        assert!((logarithmic[0] - 10.0).abs() < 1e-4);
+        assert_eq!(logarithmic[1..], [100.0; 3]);
+    }
+}
@@ -0,0 +1,592 @@
+use winit::dpi::PhysicalSize;
+
+use crate::binding::Binding;
+use crate::camera::Camera;
+use crate::instance::{Instance, InstanceBuffer};
+use crate::layouts::Layouts;
+use crate::model::AlphaMode;
+use crate::scene::Scene;
+use crate::texture::Texture;
+use crate::vertex::Vertex;
+
+/// Most kernel samples taken per pixel, by `SsaoQuality::High`.
+pub const MAX_SSAO_SAMPLES: usize = 32;
+/// Width and height of the tiled noise texture rotating the kernel.
+const NOISE_SIZE: u32 = 4;
+const NORMAL_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const OCCLUSION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+/// Number of kernel samples taken per pixel.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SsaoQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl SsaoQuality {
+    pub fn sample_count(self) -> u32 {
+        return match self {
+            SsaoQuality::Low => 8,
+            SsaoQuality::Medium => 16,
+            SsaoQuality::High => MAX_SSAO_SAMPLES as u32,
+        };
+    }
+
+    /// The next preset, wrapping around, for cycling through them at runtime.
+    pub fn next(self) -> SsaoQuality {
+        return match self {
+            SsaoQuality::Low => SsaoQuality::Medium,
+            SsaoQuality::Medium => SsaoQuality::High,
+            SsaoQuality::High => SsaoQuality::Low,
+        };
+    }
+}
+
+/// Screen-space ambient occlusion settings, they can be changed between frames.
+#[derive(Copy, Clone, Debug)]
+pub struct SsaoConfig {
+    pub enabled: bool,
+    pub quality: SsaoQuality,
+    /// Distance around a surface searched for occluders, in world units.
+    pub radius: f32,
+    /// Depth difference below which an occluder is ignored, keeps flat surfaces from
+    /// occluding themselves.
+    pub bias: f32,
+    /// Exponent applied to the ambient term, above 1 darkens occluded areas further.
+    pub intensity: f32,
+    /// Shows the raw, unblurred occlusion instead of the shaded frame, only while `enabled`.
+    pub debug: bool,
+}
+
+impl Default for SsaoConfig {
+    fn default() -> Self {
+        return SsaoConfig {
+            enabled: false,
+            quality: SsaoQuality::Medium,
+            radius: 0.5,
+            bias: 0.025,
+            intensity: 1.0,
+            debug: false,
+        };
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsaoUniform {
+    view_proj: [[f32; 4]; 4],
+    inverse_view_proj: [[f32; 4]; 4],
+    camera_position: [f32; 4],
+    camera_direction: [f32; 4],
+    radius: f32,
+    bias: f32,
+    intensity: f32,
+    sample_count: u32,
+    kernel: [[f32; 4]; MAX_SSAO_SAMPLES],
+}
+
+/// Small deterministic generator, so the kernel and noise are the same on every run.
+struct XorShift(u32);
+
+impl XorShift {
+    /// Uniform in [0, 1).
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        return (self.0 >> 8) as f32 / (1 << 24) as f32;
+    }
+}
+
+/// `count` points in the unit hemisphere around +z, denser towards the origin so occluders
+/// close to the surface weigh more.
+fn ssao_kernel(count: u32) -> [[f32; 4]; MAX_SSAO_SAMPLES] {
+    let mut random = XorShift(0x9e37_79b9);
+    let mut kernel = [[0.0; 4]; MAX_SSAO_SAMPLES];
+    let count = (count as usize).min(MAX_SSAO_SAMPLES);
+    for (i, sample) in kernel[..count].iter_mut().enumerate() {
+        let direction = glam::vec3(
+            random.next() * 2.0 - 1.0,
+            random.next() * 2.0 - 1.0,
+            random.next(),
+        )
+        .normalize_or(glam::Vec3::Z);
+        let t = i as f32 / count as f32;
+        let scale = 0.1 + 0.9 * t * t;
+        *sample = (direction * random.next() * scale).extend(0.0).into();
+    }
+    return kernel;
+}
+
+/// Screen-space ambient occlusion. A prepass draws world normals and view depth of the scene,
+/// occlusion is estimated from them with a hemisphere kernel and then blurred without crossing
+/// edges, ready for the main pass to darken ambient light with.
+pub struct Ssao {
+    pub normal_pipeline: wgpu::RenderPipeline,
+    pub ssao_pipeline: wgpu::RenderPipeline,
+    pub blur_pipeline: wgpu::RenderPipeline,
+    /// Draws `raw_occlusion` into the output for `SsaoConfig::debug`.
+    pub debug_pipeline: wgpu::RenderPipeline,
+    pub ssao_bind_group_layout: wgpu::BindGroupLayout,
+    pub blur_bind_group_layout: wgpu::BindGroupLayout,
+    pub debug_bind_group_layout: wgpu::BindGroupLayout,
+    pub ssao_bind_group: wgpu::BindGroup,
+    pub blur_bind_group: wgpu::BindGroup,
+    pub debug_bind_group: wgpu::BindGroup,
+    pub params_buffer: wgpu::Buffer,
+    pub noise: Texture,
+    /// World normal in rgb and distance along the camera direction in a. Depth is kept here
+    /// because depth textures can only be read with comparisons on GL.
+    pub normal_depth: Texture,
+    pub raw_occlusion: Texture,
+    /// Blurred occlusion, 1 where ambient light is unoccluded. Cleared to 1 while disabled.
+    pub occlusion: Texture,
+}
+
+impl Ssao {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layouts: &Layouts,
+        size: PhysicalSize<u32>,
+        output_format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let params_entry = wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<SsaoUniform>() as u64),
+            },
+            count: None,
+        };
+        let ssao_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[texture_entry(0), texture_entry(1), params_entry],
+            });
+        let blur_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[texture_entry(0), params_entry, texture_entry(3)],
+            });
+        let debug_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[texture_entry(3)],
+            });
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<SsaoUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let noise = Ssao::create_noise_texture(device, queue);
+
+        let normal_shader =
+            device.create_shader_module(wgpu::include_wgsl!("./shaders/normals.wgsl"));
+        let normal_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&layouts.globals_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let normal_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&normal_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &normal_shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[Vertex::desc(), Instance::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &normal_shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(NORMAL_DEPTH_FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/ssao.wgsl"));
+        let create_pipeline = |layout: &wgpu::BindGroupLayout, entry_point, format| {
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            });
+            return device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some(entry_point),
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::TextureFormat::into(format))],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+        };
+        let ssao_pipeline = create_pipeline(&ssao_bind_group_layout, "fs_ssao", OCCLUSION_FORMAT);
+        let blur_pipeline = create_pipeline(&blur_bind_group_layout, "fs_blur", OCCLUSION_FORMAT);
+        let debug_pipeline = create_pipeline(&debug_bind_group_layout, "fs_debug", output_format);
+
+        let normal_depth = Texture::create_render_target(device, size, NORMAL_DEPTH_FORMAT);
+        let raw_occlusion = Texture::create_render_target(device, size, OCCLUSION_FORMAT);
+        let occlusion = Texture::create_render_target(device, size, OCCLUSION_FORMAT);
+        let (ssao_bind_group, blur_bind_group) = Ssao::create_bind_groups(
+            device,
+            &ssao_bind_group_layout,
+            &blur_bind_group_layout,
+            &params_buffer,
+            &noise,
+            &normal_depth,
+            &raw_occlusion,
+        );
+        let debug_bind_group =
+            Ssao::create_debug_bind_group(device, &debug_bind_group_layout, &raw_occlusion);
+        return Ssao {
+            normal_pipeline,
+            ssao_pipeline,
+            blur_pipeline,
+            debug_pipeline,
+            ssao_bind_group_layout,
+            blur_bind_group_layout,
+            debug_bind_group_layout,
+            ssao_bind_group,
+            blur_bind_group,
+            debug_bind_group,
+            params_buffer,
+            noise,
+            normal_depth,
+            raw_occlusion,
+            occlusion,
+        };
+    }
+
+    /// Random rotation vectors around +z, tiled over the screen.
+    fn create_noise_texture(device: &wgpu::Device, queue: &wgpu::Queue) -> Texture {
+        let mut random = XorShift(0x2545_f491);
+        let pixels: Vec<u8> = (0..NOISE_SIZE * NOISE_SIZE)
+            .flat_map(|_| {
+                let x = random.next();
+                let y = random.next();
+                [(x * 255.0) as u8, (y * 255.0) as u8, 128, 255]
+            })
+            .collect();
+        let texture_size = wgpu::Extent3d {
+            width: NOISE_SIZE,
+            height: NOISE_SIZE,
+            depth_or_array_layers: 1,
+        };
+        // linear, the vectors are decoded in the shader
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &pixels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * NOISE_SIZE),
+                rows_per_image: Some(NOISE_SIZE),
+            },
+            texture_size,
+        );
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+        return Texture {
+            texture,
+            texture_view,
+            sampler,
+        };
+    }
+
+    fn create_bind_groups(
+        device: &wgpu::Device,
+        ssao_layout: &wgpu::BindGroupLayout,
+        blur_layout: &wgpu::BindGroupLayout,
+        params_buffer: &wgpu::Buffer,
+        noise: &Texture,
+        normal_depth: &Texture,
+        raw_occlusion: &Texture,
+    ) -> (wgpu::BindGroup, wgpu::BindGroup) {
+        let ssao_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: ssao_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&normal_depth.texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&noise.texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let blur_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: blur_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&normal_depth.texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&raw_occlusion.texture_view),
+                },
+            ],
+        });
+        return (ssao_bind_group, blur_bind_group);
+    }
+
+    fn create_debug_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        raw_occlusion: &Texture,
+    ) -> wgpu::BindGroup {
+        return device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&raw_occlusion.texture_view),
+            }],
+        });
+    }
+
+    /// Recreates the screen sized buffers, the main pass has to rebind `occlusion` afterwards.
+    pub fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        self.normal_depth = Texture::create_render_target(device, size, NORMAL_DEPTH_FORMAT);
+        self.raw_occlusion = Texture::create_render_target(device, size, OCCLUSION_FORMAT);
+        self.occlusion = Texture::create_render_target(device, size, OCCLUSION_FORMAT);
+        (self.ssao_bind_group, self.blur_bind_group) = Ssao::create_bind_groups(
+            device,
+            &self.ssao_bind_group_layout,
+            &self.blur_bind_group_layout,
+            &self.params_buffer,
+            &self.noise,
+            &self.normal_depth,
+            &self.raw_occlusion,
+        );
+        self.debug_bind_group = Ssao::create_debug_bind_group(
+            device,
+            &self.debug_bind_group_layout,
+            &self.raw_occlusion,
+        );
+    }
+
+    /// Draws normals and depth of every opaque mesh into `normal_depth`, depth tested against
+    /// `depth_texture`. Cutout and blended meshes are left out as the prepass would see them
+    /// as solid.
+    pub fn draw_normals(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        depth_texture: &Texture,
+        globals: &Binding,
+        scene: &Scene,
+        instance_buffers: &[InstanceBuffer],
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.normal_depth.texture_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    // a zero normal marks the background
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth_texture.texture_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.normal_pipeline);
+        render_pass.set_bind_group(globals.bind_index, &globals.bind_group, &[]);
+        for ((model, instances), instance_buffer) in scene.models.iter().zip(instance_buffers) {
+            for mesh in model.meshes.iter() {
+                if model.materials[mesh.material_id].alpha_mode != AlphaMode::Opaque {
+                    continue;
+                }
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+                render_pass
+                    .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..mesh.len_indicies as u32, 0, 0..instances.len() as u32);
+            }
+        }
+    }
+
+    /// Estimates occlusion from the prepass and blurs it into `occlusion`.
+    pub fn apply(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        config: &SsaoConfig,
+    ) {
+        let params = SsaoUniform {
+            view_proj: camera.view_matrix.to_cols_array_2d(),
+            inverse_view_proj: camera.view_matrix.inverse().to_cols_array_2d(),
+            camera_position: camera.position.extend(1.0).into(),
+            camera_direction: camera.direction.extend(0.0).into(),
+            radius: config.radius,
+            bias: config.bias,
+            intensity: config.intensity,
+            sample_count: config.quality.sample_count(),
+            kernel: ssao_kernel(config.quality.sample_count()),
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+        Ssao::fullscreen_pass(
+            encoder,
+            &self.ssao_pipeline,
+            &self.ssao_bind_group,
+            &self.raw_occlusion.texture_view,
+        );
+        Ssao::fullscreen_pass(
+            encoder,
+            &self.blur_pipeline,
+            &self.blur_bind_group,
+            &self.occlusion.texture_view,
+        );
+    }
+
+    /// Fills `occlusion` with 1, leaving ambient light untouched.
+    pub fn clear(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.occlusion.texture_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+    }
+
+    /// Draws `raw_occlusion` as greyscale into `target`, in place of the tonemapped frame.
+    pub fn draw_debug(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        Ssao::fullscreen_pass(
+            encoder,
+            &self.debug_pipeline,
+            &self.debug_bind_group,
+            target,
+        );
+    }
+
+    fn fullscreen_pass(
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        bind_group: &wgpu::BindGroup,
+        target: &wgpu::TextureView,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_stays_in_unit_hemisphere() {
+        let count = SsaoQuality::High.sample_count();
+        let kernel = ssao_kernel(count);
+        for sample in &kernel[..count as usize] {
+            let sample = glam::Vec4::from(*sample).truncate();
+            assert!(sample.z >= 0.0, "{sample} below the surface");
+            assert!(sample.length() <= 1.0, "{sample} outside the unit sphere");
+        }
+        // samples past the preset count are unused and left at zero
+        let medium = ssao_kernel(SsaoQuality::Medium.sample_count());
+        assert!(medium[16..].iter().all(|sample| *sample == [0.0; 4]));
+    }
+}
@@ -1,117 +1,290 @@
+use std::ops::Range;
 use std::sync::Arc;
 
 use winit::dpi::PhysicalSize;
 use winit::window::Window;
 
-use crate::binding::Binding;
-use crate::binding::TextureBinding;
-use crate::camera::Camera;
-use crate::instance::Instance;
+use crate::binding::{Binding, TextureBinding};
+use crate::bloom::{Bloom, BloomConfig};
+use crate::camera::{Camera, CAMERA_FAR_PLANE, CAMERA_NEAR_PLANE};
+use crate::error::{Error, Result};
+use crate::exposure::AutoExposure;
+use crate::instance::{Instance, InstanceBuffer};
 use crate::layouts::Layouts;
-use crate::light::Light;
-use crate::model::create_plane;
-use crate::model::MaterialParams;
-use crate::model::Model;
+use crate::light::{
+    Light, LightsRaw, SpotLight, SpotLightsRaw, SunRaw, MAX_LIGHTS, MAX_SPOT_LIGHTS,
+};
+use crate::mipmap::MipmapGenerator;
+use crate::model::{AlphaMode, Material, Mesh, Shading};
+use crate::offscreen::Offscreen;
+use crate::offscreen::OFFSCREEN_FORMAT;
+use crate::scene::Scene;
+use crate::scene_file::CameraDesc;
+use crate::scene_file::SceneFile;
+use crate::shadow::{
+    cube_face_view_projs, shadow_binding, spot_view_proj, sun_raw, CascadeConfig, PointShadows,
+    ShadowConfig, ShadowParamsRaw, SpotShadows, SunShadows,
+};
+use crate::ssao::{Ssao, SsaoConfig};
 use crate::texture::Texture;
+use crate::tonemap::{ToneMapConfig, Tonemap, HDR_FORMAT};
 use crate::vertex::Vertex;
 use crate::vertex::CUBE_INDICES;
 use crate::vertex::CUBE_VERTICES;
 
+fn aspect_ratio(size: PhysicalSize<u32>) -> f32 {
+    return size.width.max(1) as f32 / size.height.max(1) as f32;
+}
+
+/// Where `State::render` draws a frame to.
+pub enum Target {
+    Window {
+        window: Arc<Window>,
+        surface: wgpu::Surface<'static>,
+    },
+    Offscreen(Offscreen),
+}
+
+/// Settings that have to be known when the device, surface and shadow map are created.
+#[derive(Copy, Clone, Debug)]
+pub struct RenderConfig {
+    pub backends: wgpu::Backends,
+    /// Use a software adapter (llvmpipe, lavapipe, WARP) instead of a GPU.
+    pub force_fallback_adapter: bool,
+    pub present_mode: wgpu::PresentMode,
+    /// Width and height of every shadow cube map face, directional light cascade and spot light
+    /// shadow map.
+    pub shadow_map_size: u32,
+    pub cascades: CascadeConfig,
+    pub shadows: ShadowConfig,
+    pub tonemapping: ToneMapConfig,
+    pub bloom: BloomConfig,
+    pub ssao: SsaoConfig,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        return RenderConfig {
+            backends: wgpu::Backends::all(),
+            force_fallback_adapter: false,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            shadow_map_size: 1024,
+            cascades: CascadeConfig::default(),
+            shadows: ShadowConfig::default(),
+            tonemapping: ToneMapConfig::default(),
+            bloom: BloomConfig::default(),
+            ssao: SsaoConfig::default(),
+        };
+    }
+}
+
 pub struct State {
-    pub window: Arc<Window>,
+    pub config: RenderConfig,
+    pub target: Target,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub size: winit::dpi::PhysicalSize<u32>,
-    pub surface: wgpu::Surface<'static>,
     pub surface_format: wgpu::TextureFormat,
+    /// Draws `Shading::Phong` materials.
     pub render_pipeline: wgpu::RenderPipeline,
+    /// Draws `Shading::Pbr` materials.
+    pub pbr_pipeline: wgpu::RenderPipeline,
+    /// Blends `AlphaMode::Blend` Phong materials over the opaque ones without writing depth.
+    pub transparent_pipeline: wgpu::RenderPipeline,
+    /// Like `transparent_pipeline`, for `Shading::Pbr` materials.
+    pub transparent_pbr_pipeline: wgpu::RenderPipeline,
     pub light_pipeline: wgpu::RenderPipeline,
     pub shadow_pipeline: wgpu::RenderPipeline,
+    pub sun_shadow_pipeline: wgpu::RenderPipeline,
+    /// Shadow pipelines of `AlphaMode::Mask` materials, discarding what the main pass discards.
+    pub cutout_shadow_pipeline: wgpu::RenderPipeline,
+    pub sun_cutout_shadow_pipeline: wgpu::RenderPipeline,
     pub layouts: Layouts,
+    /// Shared by every texture load, so its pipelines are only built once.
+    pub mipmaps: MipmapGenerator,
     pub camera: Camera,
-    pub light: Light,
-    pub light_uniform: Binding,
     pub depth_texture: Texture,
-    pub models: Vec<(Model, Vec<Instance>)>,
-    pub material_uniform: Binding,
-    pub shadow_cube_map: Texture,
+    /// The scene is lit into this texture, then tonemapped into the target.
+    pub hdr_texture: Texture,
+    pub ssao: Ssao,
+    pub bloom: Bloom,
+    pub tonemap: Tonemap,
+    /// `None` when the adapter cannot run compute shaders.
+    pub auto_exposure: Option<AutoExposure>,
+    pub scene: Scene,
+    pub point_shadows: PointShadows,
+    pub sun_shadows: SunShadows,
+    pub spot_shadows: SpotShadows,
+    /// Shadow maps of every light kind and the ambient occlusion, recreated whenever
+    /// `point_shadows` or `spot_shadows` grow or the target is resized.
+    pub shadow_binding: TextureBinding,
     pub globals_uniform: Binding,
+    pub light_cube_buffers: (wgpu::Buffer, wgpu::Buffer),
+    /// One per entry of `scene.models`, synced with the scene at the start of every frame.
+    pub instance_buffers: Vec<InstanceBuffer>,
 }
 
 impl State {
-    pub async fn new(window: Arc<Window>) -> State {
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    pub async fn new(window: Arc<Window>, config: RenderConfig) -> Result<State> {
+        let instance = State::create_instance(&config);
+        let surface = instance.create_surface(window.clone())?;
         let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: config.force_fallback_adapter,
+                ..Default::default()
+            })
             .await
-            .unwrap();
-        let (device, queue) = adapter
+            .ok_or(Error::NoAdapter)?;
+        let (device, queue) = State::request_device(&adapter).await?;
+        let compute_shaders = State::supports_compute_shaders(&adapter);
+        let size = window.inner_size();
+
+        let cap = surface.get_capabilities(&adapter);
+        let surface_format = cap.formats[0];
+        let state = State::with_target(
+            config,
+            device,
+            queue,
+            size,
+            surface_format,
+            compute_shaders,
+            Target::Window { window, surface },
+        );
+        state.configure_surface();
+        return Ok(state);
+    }
+
+    /// Creates a state that renders into an offscreen texture instead of a window.
+    /// Together with `RenderConfig::force_fallback_adapter` frames can be produced on
+    /// machines without a GPU or a display.
+    pub async fn new_headless(size: PhysicalSize<u32>, config: RenderConfig) -> Result<State> {
+        let instance = State::create_instance(&config);
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                force_fallback_adapter: config.force_fallback_adapter,
+                ..Default::default()
+            })
+            .await
+            .ok_or(Error::NoAdapter)?;
+        let (device, queue) = State::request_device(&adapter).await?;
+        let compute_shaders = State::supports_compute_shaders(&adapter);
+        let offscreen = Offscreen::new(&device, size);
+        return Ok(State::with_target(
+            config,
+            device,
+            queue,
+            offscreen.size,
+            OFFSCREEN_FORMAT,
+            compute_shaders,
+            Target::Offscreen(offscreen),
+        ));
+    }
+
+    fn create_instance(config: &RenderConfig) -> wgpu::Instance {
+        return wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: config.backends,
+            ..Default::default()
+        });
+    }
+
+    async fn request_device(adapter: &wgpu::Adapter) -> Result<(wgpu::Device, wgpu::Queue)> {
+        let device_and_queue = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
+                    // software and GL adapters do not reach the default limits
+                    required_limits: adapter.limits(),
                     ..Default::default()
                 },
                 None,
             )
-            .await
-            .unwrap();
-        let size = window.inner_size();
+            .await?;
+        return Ok(device_and_queue);
+    }
 
-        let surface = instance.create_surface(window.clone()).unwrap();
-        let cap = surface.get_capabilities(&adapter);
-        let surface_format = cap.formats[0];
+    fn supports_compute_shaders(adapter: &wgpu::Adapter) -> bool {
+        let compute_shaders = adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+        if !compute_shaders {
+            log::warn!("adapter has no compute shaders, auto exposure is disabled");
+        }
+        return compute_shaders;
+    }
 
+    fn with_target(
+        config: RenderConfig,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        size: PhysicalSize<u32>,
+        surface_format: wgpu::TextureFormat,
+        compute_shaders: bool,
+        target: Target,
+    ) -> State {
         let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/main.wgsl"));
         let light_shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/light.wgsl"));
-        let shadow_shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/shadow.wgsl"));
+        let shadow_shader =
+            device.create_shader_module(wgpu::include_wgsl!("./shaders/shadow.wgsl"));
 
-        let models: Vec<(Model, Vec<Instance>)> = Vec::new();
+        let scene = Scene::default();
 
-        let mut camera = Camera::new(
-            glam::Vec3::new(0.0, 1.0, 5.0),
-            f32::to_radians(0.0),
-            f32::to_radians(-90.0),
-            2.5,
-            f32::to_radians(60.0),
-            size.width as f32 / size.height as f32,
-        );
-        let light = Light {
-            pos: [-1.0, 6.0, 1.0],
-            _padding: 0,
-            color: [1.0, 1.0, 1.0],
-            _padding2: 0,
-            view_proj: [[0.0; 4]; 4],
-        };
+        let camera = CameraDesc::default().to_camera(aspect_ratio(size));
         let layouts = Layouts::new(&device);
+        let mipmaps = MipmapGenerator::new(&device);
         let globals_uniform = Binding::create_binding(
             &device,
             &layouts.globals_bind_group_layout,
             vec![
                 bytemuck::cast_slice(&[camera.to_camera_raw()]),
-                bytemuck::cast_slice(&[light]),
+                bytemuck::cast_slice(&[LightsRaw::new(&scene.lights)]),
+                bytemuck::cast_slice(&[sun_raw(
+                    None,
+                    &camera,
+                    CAMERA_NEAR_PLANE,
+                    &config.cascades,
+                    config.shadow_map_size,
+                )]),
+                bytemuck::cast_slice(&[SpotLightsRaw::new(&scene.spot_lights)]),
+                bytemuck::cast_slice(&[ShadowParamsRaw::new(&config.shadows)]),
             ],
             0,
         );
-        let light_uniform = Binding::create_binding(
+
+        let depth_texture = Texture::create_depth_texture(&device, size, 1);
+        let hdr_texture = Texture::create_render_target(&device, size, HDR_FORMAT);
+        let bloom = Bloom::new(&device, &hdr_texture);
+        let tonemap = Tonemap::new(&device, &hdr_texture, surface_format.add_srgb_suffix());
+        let auto_exposure = compute_shaders.then(|| AutoExposure::new(&device, &hdr_texture));
+        let point_shadows = PointShadows::new(
             &device,
-            &layouts.light_bind_group_layout,
-            vec![bytemuck::cast_slice(&[light])],
-            0,
+            &layouts,
+            config.shadow_map_size,
+            scene.lights.len().min(MAX_LIGHTS),
         );
-        let material_uniform = Binding::create_binding(
+        let sun_shadows = SunShadows::new(&device, &layouts, config.shadow_map_size);
+        let spot_shadows = SpotShadows::new(
             &device,
-            &layouts.material_bind_group_layout,
-            vec![bytemuck::cast_slice(&[MaterialParams::default()])],
-            2,
+            &layouts,
+            config.shadow_map_size,
+            scene.spot_lights.len().min(MAX_SPOT_LIGHTS),
         );
-
-        let depth_texture = Texture::create_depth_texture(&device, size, 1);
-        let shadow_cube_map = Texture::create_cube_depth_texture(
+        let ssao = Ssao::new(
             &device,
-            PhysicalSize {
-                width: 1024,
-                height: 1024,
-            },
+            &queue,
+            &layouts,
+            size,
+            surface_format.add_srgb_suffix(),
+        );
+        let shadow_binding = shadow_binding(
+            &device,
+            &layouts,
+            &point_shadows,
+            &sun_shadows,
+            &spot_shadows,
+            &ssao.occlusion,
         );
+        let light_cube_buffers = Vertex::make_buffers(&device, CUBE_VERTICES, CUBE_INDICES);
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -137,20 +310,61 @@ impl State {
                 bind_group_layouts: &[&layouts.light_bind_group_layout],
                 push_constant_ranges: &[],
             });
+        let cutout_shadow_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &layouts.light_bind_group_layout,
+                    &layouts.texture_bind_group_layout,
+                    &layouts.material_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
         let render_pipeline = State::create_render_pipeline(
             &device,
             &render_pipeline_layout,
             &shader,
+            "fs_main",
             &[Vertex::desc(), Instance::desc()],
-            surface_format,
+            HDR_FORMAT,
+            None,
+        );
+        let pbr_pipeline = State::create_render_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            "fs_pbr",
+            &[Vertex::desc(), Instance::desc()],
+            HDR_FORMAT,
+            None,
+        );
+        let transparent_pipeline = State::create_render_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            "fs_main",
+            &[Vertex::desc(), Instance::desc()],
+            HDR_FORMAT,
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+        );
+        let transparent_pbr_pipeline = State::create_render_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            "fs_pbr",
+            &[Vertex::desc(), Instance::desc()],
+            HDR_FORMAT,
+            Some(wgpu::BlendState::ALPHA_BLENDING),
         );
 
         let light_pipeline = State::create_render_pipeline(
             &device,
             &light_pipeline_layout,
             &light_shader,
+            "fs_main",
             &[Vertex::desc()],
-            surface_format,
+            HDR_FORMAT,
+            None,
         );
 
         let shadow_pipeline = State::create_shadow_pipeline(
@@ -158,104 +372,111 @@ impl State {
             &shadow_pipeline_layout,
             &shadow_shader,
             &[Vertex::desc(), Instance::desc()],
+            Some("fs_main"),
+            Some(wgpu::Face::Front),
+        );
+        // cascades keep the rasterized depth, linear under an orthographic projection, and
+        // render both faces so single sided planes cast shadows too
+        let sun_shadow_pipeline = State::create_shadow_pipeline(
+            &device,
+            &shadow_pipeline_layout,
+            &shadow_shader,
+            &[Vertex::desc(), Instance::desc()],
+            None,
+            None,
+        );
+        let cutout_shadow_pipeline = State::create_shadow_pipeline(
+            &device,
+            &cutout_shadow_pipeline_layout,
+            &shadow_shader,
+            &[Vertex::desc(), Instance::desc()],
+            Some("fs_cutout"),
+            Some(wgpu::Face::Front),
+        );
+        let sun_cutout_shadow_pipeline = State::create_shadow_pipeline(
+            &device,
+            &cutout_shadow_pipeline_layout,
+            &shadow_shader,
+            &[Vertex::desc(), Instance::desc()],
+            Some("fs_sun_cutout"),
+            None,
         );
 
-        let mut state = State {
-            window,
+        return State {
+            config,
+            target,
             device,
             queue,
             size,
-            surface,
             surface_format,
             render_pipeline,
+            pbr_pipeline,
+            transparent_pipeline,
+            transparent_pbr_pipeline,
             camera,
             depth_texture,
+            hdr_texture,
+            ssao,
+            bloom,
+            tonemap,
+            auto_exposure,
             light_pipeline,
-            material_uniform,
             layouts,
+            mipmaps,
             shadow_pipeline,
-            models,
-            shadow_cube_map,
+            sun_shadow_pipeline,
+            cutout_shadow_pipeline,
+            sun_cutout_shadow_pipeline,
+            scene,
+            point_shadows,
+            sun_shadows,
+            spot_shadows,
+            shadow_binding,
             globals_uniform,
-            light,
-            light_uniform,
+            light_cube_buffers,
+            instance_buffers: Vec::new(),
         };
-        state.configure_surface();
-        state.create_scene();
+    }
 
-        return state;
+    /// Replaces the scene and camera with the ones described in a scene file.
+    pub fn load_scene(&mut self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        return self.set_scene(&SceneFile::open(path)?);
     }
 
-    pub fn create_scene(&mut self) {
-        let model = Model::load_model("./assets/model.obj", &self.device, &self.queue);
-        let instance = vec![
-            Instance::from_translation_rotation_scale(
-                [1.0, 1.0, 1.0].into(),
-                glam::f32::Quat::from_rotation_x(10.0f32.to_radians()),
-                [0.33, 0.33, 0.33].into(),
-            ),
-            Instance::from_translation_rotation_scale(
-                [4.0, 3.0, 1.0].into(),
-                glam::f32::Quat::from_rotation_x(10.0f32.to_radians()),
-                [0.33, 0.33, 0.33].into(),
-            ),
-            Instance::from_translation_rotation_scale(
-                [-1.0, 3.0, -3.0].into(),
-                glam::f32::Quat::from_rotation_x(10.0f32.to_radians()),
-                [0.33, 0.33, 0.33].into(),
-            ),
-        ];
-        self.models.push((model, instance));
-        let plane1 = create_plane(&self.device, &self.queue, [124, 102, 92, 255]);
-        self.models.push((
-            plane1,
-            vec![Instance::from_translation_rotation_scale(
-                [0.0, -1.0, 0.0].into(),
-                glam::f32::Quat::from_rotation_x(f32::to_radians(0.0)),
-                [1.0, 1.0, 1.0].into(),
-            )],
-        ));
-        let plane2 = create_plane(&self.device, &self.queue, [87, 212, 193, 255]);
-        self.models.push((
-            plane2,
-            vec![Instance::from_translation_rotation_scale(
-                [5.0, 0.0, 0.0].into(),
-                glam::f32::Quat::from_rotation_z(f32::to_radians(90.0)),
-                [1.0, 1.0, 1.0].into(),
-            )],
-        ));
-        let plane3 = create_plane(&self.device, &self.queue, [87, 212, 97, 255]);
-        self.models.push((
-            plane3,
-            vec![Instance::from_translation_rotation_scale(
-                [0.0, 0.0, -5.0].into(),
-                glam::f32::Quat::from_rotation_x(f32::to_radians(90.0)),
-                [1.0, 1.0, 1.0].into(),
-            )],
-        ));
+    pub fn set_scene(&mut self, scene_file: &SceneFile) -> Result<()> {
+        self.scene = scene_file.build(&self.device, &self.queue, &self.layouts, &self.mipmaps)?;
+        self.camera = scene_file.camera.to_camera(aspect_ratio(self.size));
+        return Ok(());
     }
 
+    /// Blending pipelines test against the depth buffer without writing to it.
     pub fn create_render_pipeline(
         device: &wgpu::Device,
         layout: &wgpu::PipelineLayout,
         shader: &wgpu::ShaderModule,
+        fragment_entry_point: &str,
         vertex_buffers: &[wgpu::VertexBufferLayout],
         surface_format: wgpu::TextureFormat,
+        blend: Option<wgpu::BlendState>,
     ) -> wgpu::RenderPipeline {
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
             layout: Some(layout),
             vertex: wgpu::VertexState {
-                module: &shader,
+                module: shader,
                 entry_point: Some("vs_main"),
                 compilation_options: Default::default(),
                 buffers: vertex_buffers,
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
+                module: shader,
+                entry_point: Some(fragment_entry_point),
                 compilation_options: Default::default(),
-                targets: &[Some(surface_format.into())],
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
             }),
             primitive: wgpu::PrimitiveState {
                 topology: wgpu::PrimitiveTopology::TriangleList,
@@ -268,7 +489,7 @@ impl State {
             },
             depth_stencil: Some(wgpu::DepthStencilState {
                 format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
+                depth_write_enabled: blend.is_none(),
                 depth_compare: wgpu::CompareFunction::Less,
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
@@ -285,27 +506,29 @@ impl State {
         layout: &wgpu::PipelineLayout,
         shader: &wgpu::ShaderModule,
         vertex_buffers: &[wgpu::VertexBufferLayout],
+        fragment_entry_point: Option<&str>,
+        cull_mode: Option<wgpu::Face>,
     ) -> wgpu::RenderPipeline {
         return device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
-            layout: Some(&layout),
+            layout: Some(layout),
             vertex: wgpu::VertexState {
-                module: &shader,
+                module: shader,
                 entry_point: Some("vs_main"),
                 compilation_options: Default::default(),
                 buffers: vertex_buffers,
             },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
+            fragment: fragment_entry_point.map(|entry_point| wgpu::FragmentState {
+                module: shader,
+                entry_point: Some(entry_point),
                 compilation_options: Default::default(),
-                targets: &vec![],
+                targets: &[],
             }),
             primitive: wgpu::PrimitiveState {
                 topology: wgpu::PrimitiveTopology::TriangleList,
                 strip_index_format: None,
                 front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Front),
+                cull_mode,
                 unclipped_depth: false,
                 polygon_mode: wgpu::PolygonMode::Fill,
                 conservative: false,
@@ -315,11 +538,8 @@ impl State {
                 depth_write_enabled: true,
                 depth_compare: wgpu::CompareFunction::Less,
                 stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState {
-                    constant: 2,
-                    slope_scale: 1.0,
-                    clamp: 0.0,
-                },
+                // biased per light when the shadow maps are sampled, see `ShadowSettings`
+                bias: wgpu::DepthBiasState::default(),
             }),
             multisample: wgpu::MultisampleState::default(),
             multiview: None,
@@ -327,76 +547,173 @@ impl State {
         });
     }
 
+    /// Resizes the render target and everything that depends on its size: the surface or
+    /// offscreen texture, the depth, HDR and ambient occlusion textures and the camera
+    /// projection. A minimised (0x0) window only records the size, frames are skipped until it
+    /// is restored.
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        self.size = new_size;
+        if self.is_minimized() {
+            return;
+        }
+        if let Target::Offscreen(offscreen) = &mut self.target {
+            *offscreen = Offscreen::new(&self.device, new_size);
+        }
+        self.configure_surface();
+        self.depth_texture = Texture::create_depth_texture(&self.device, new_size, 1);
+        self.hdr_texture = Texture::create_render_target(&self.device, new_size, HDR_FORMAT);
+        self.ssao.resize(&self.device, new_size);
+        self.shadow_binding = shadow_binding(
+            &self.device,
+            &self.layouts,
+            &self.point_shadows,
+            &self.sun_shadows,
+            &self.spot_shadows,
+            &self.ssao.occlusion,
+        );
+        self.bloom.set_input(&self.device, &self.hdr_texture);
+        self.tonemap.set_input(&self.device, &self.hdr_texture);
+        if let Some(auto_exposure) = &mut self.auto_exposure {
+            auto_exposure.set_input(&self.device, &self.hdr_texture);
+        }
+        self.camera.set_aspect_ratio(aspect_ratio(new_size));
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        return self.size.width == 0 || self.size.height == 0;
+    }
+
     pub fn configure_surface(&self) {
+        let Target::Window { surface, .. } = &self.target else {
+            return;
+        };
+        if self.is_minimized() {
+            return;
+        }
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: self.surface_format,
             width: self.size.width,
             height: self.size.height,
-            present_mode: wgpu::PresentMode::AutoVsync,
+            present_mode: self.config.present_mode,
             desired_maximum_frame_latency: 2,
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
             view_formats: vec![self.surface_format.add_srgb_suffix()],
         };
-        self.surface.configure(&self.device, &surface_config);
+        surface.configure(&self.device, &surface_config);
     }
 
+    /// Makes `instance_buffers` match `scene.models`, uploading only instances that changed.
+    pub fn update_instance_buffers(&mut self) {
+        self.instance_buffers.truncate(self.scene.models.len());
+        for (i, (_, instances)) in self.scene.models.iter().enumerate() {
+            match self.instance_buffers.get_mut(i) {
+                Some(instance_buffer) => {
+                    instance_buffer.update(&self.device, &self.queue, instances)
+                }
+                None => self.instance_buffers.push(InstanceBuffer::new(
+                    &self.device,
+                    &self.queue,
+                    instances,
+                )),
+            }
+        }
+    }
+
+    /// Pipeline for the shading and alpha mode of `material`.
+    fn material_pipeline(&self, material: &Material) -> &wgpu::RenderPipeline {
+        return match (material.shading, material.alpha_mode) {
+            (Shading::Phong, AlphaMode::Blend) => &self.transparent_pipeline,
+            (Shading::Pbr, AlphaMode::Blend) => &self.transparent_pbr_pipeline,
+            (Shading::Phong, _) => &self.render_pipeline,
+            (Shading::Pbr, _) => &self.pbr_pipeline,
+        };
+    }
+
+    /// Draws every model with the pipeline of its materials' shading, except for blended
+    /// meshes which `draw_transparent` draws afterwards.
     pub fn draw_model_instanced(&mut self, render_pass: &mut wgpu::RenderPass) {
         render_pass.set_bind_group(
             self.globals_uniform.bind_index,
             &self.globals_uniform.bind_group,
             &[],
         );
-        let shadow_cube_map_uniform = TextureBinding::new(
-            &self.device,
-            &self.layouts.shadow_texture_bind_group_layout,
-            &self.shadow_cube_map,
-            3,
+        render_pass.set_bind_group(
+            self.shadow_binding.bind_index,
+            &self.shadow_binding.bind_group,
+            &[],
+        );
+        for ((model, instances), instance_buffer) in
+            self.scene.models.iter().zip(self.instance_buffers.iter())
+        {
+            for mesh in model.meshes.iter() {
+                let material = &model.materials[mesh.material_id];
+                if material.alpha_mode == AlphaMode::Blend {
+                    continue;
+                }
+                let instances = 0..instances.len() as u32;
+                self.draw_mesh(render_pass, mesh, material, instance_buffer, instances);
+            }
+        }
+    }
+
+    /// Draws the blended meshes of every instance, the instances farthest from the camera
+    /// first so nearer ones blend over them.
+    pub fn draw_transparent(&mut self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_bind_group(
+            self.globals_uniform.bind_index,
+            &self.globals_uniform.bind_group,
+            &[],
         );
         render_pass.set_bind_group(
-            shadow_cube_map_uniform.bind_index,
-            &shadow_cube_map_uniform.bind_group,
+            self.shadow_binding.bind_index,
+            &self.shadow_binding.bind_group,
             &[],
         );
-        for (model, instances) in self.models.iter() {
-            let instance_buffer = Instance::make_buffer(&self.device, &instances);
+        for (model_index, instance_index) in self.scene.transparent_instances(self.camera.position)
+        {
+            let model = &self.scene.models[model_index].0;
+            let instance_buffer = &self.instance_buffers[model_index];
             for mesh in model.meshes.iter() {
-                let material_id = match mesh.material_id {
-                    Some(_) => 0,
-                    None => 0,
-                };
-                let diffuse_texture_uniform = TextureBinding::new(
-                    &self.device,
-                    &self.layouts.texture_bind_group_layout,
-                    &model.materials[material_id].diffuse_texture,
-                    1,
-                );
-                self.material_uniform.update_buffer(
-                    &self.queue,
-                    vec![bytemuck::cast_slice(&[model.materials[material_id].params])],
-                );
-                render_pass.set_bind_group(
-                    diffuse_texture_uniform.bind_index,
-                    &diffuse_texture_uniform.bind_group,
-                    &[],
-                );
-                render_pass.set_bind_group(
-                    self.material_uniform.bind_index,
-                    &self.material_uniform.bind_group,
-                    &[],
-                );
-                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-                render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
-                render_pass
-                    .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                render_pass.draw_indexed(0..mesh.len_indicies as u32, 0, 0..instances.len() as u32);
+                let material = &model.materials[mesh.material_id];
+                if material.alpha_mode != AlphaMode::Blend {
+                    continue;
+                }
+                let instances = instance_index..instance_index + 1;
+                self.draw_mesh(render_pass, mesh, material, instance_buffer, instances);
             }
         }
     }
 
+    /// Draws `instances` of a mesh with its material, the globals and shadows have to be bound.
+    fn draw_mesh(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        mesh: &Mesh,
+        material: &Material,
+        instance_buffer: &InstanceBuffer,
+        instances: Range<u32>,
+    ) {
+        render_pass.set_pipeline(self.material_pipeline(material));
+        render_pass.set_bind_group(
+            material.texture_binding.bind_index,
+            &material.texture_binding.bind_group,
+            &[],
+        );
+        render_pass.set_bind_group(
+            material.uniform.bind_index,
+            &material.uniform.bind_group,
+            &[],
+        );
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+        render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..mesh.len_indicies as u32, 0, instances);
+    }
+
+    /// Draws an emissive cube at every light.
     pub fn draw_light(&mut self, render_pass: &mut wgpu::RenderPass) {
-        let (vertex_buffer, index_buffer) =
-            Vertex::make_buffers(&self.device, CUBE_VERTICES, CUBE_INDICES);
+        let (vertex_buffer, index_buffer) = &self.light_cube_buffers;
         render_pass.set_bind_group(
             self.globals_uniform.bind_index,
             &self.globals_uniform.bind_group,
@@ -404,112 +721,289 @@ impl State {
         );
         render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
         render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        render_pass.draw_indexed(0..CUBE_INDICES.len() as u32, 0, 0..1 as u32);
+        let lights = self.scene.lights.len().min(MAX_LIGHTS) as u32;
+        render_pass.draw_indexed(0..CUBE_INDICES.len() as u32, 0, 0..lights);
     }
 
     pub fn shadow_pass(&mut self, encoder: &mut wgpu::CommandEncoder) {
-        let light_pos = glam::vec3(self.light.pos[0], self.light.pos[1], self.light.pos[2]);
-        let views = [
-            glam::Mat4::look_at_lh(light_pos, light_pos + glam::Vec3::X, glam::Vec3::Y), // +X
-            glam::Mat4::look_at_lh(light_pos, light_pos - glam::Vec3::X, glam::Vec3::Y), // -X
-            glam::Mat4::look_at_lh(light_pos, light_pos + glam::Vec3::Y, -glam::Vec3::Z), //  +Y
-            glam::Mat4::look_at_lh(light_pos, light_pos - glam::Vec3::Y, glam::Vec3::Z), // -Y
-            glam::Mat4::look_at_lh(light_pos, light_pos + glam::Vec3::Z, glam::Vec3::Y), // +Z
-            glam::Mat4::look_at_lh(light_pos, light_pos - glam::Vec3::Z, glam::Vec3::Y), // -Z
-        ];
-        let perspective = glam::f32::Mat4::perspective_lh(f32::to_radians(90.0), 1.0, 0.1, 100.0);
-        for i in 0..6 {
-            let view_matrix = perspective * views[i];
-            self.light.view_proj = view_matrix.to_cols_array_2d();
-            self.light_uniform
-                .update_buffer(&self.queue, vec![bytemuck::cast_slice(&[self.light])]);
-            let new_light_uniform = Binding::create_binding(
+        let lights = &self.scene.lights[..self.scene.lights.len().min(MAX_LIGHTS)];
+        let spot_lights = self.spot_lights();
+        let grow_point_shadows = lights.len() > self.point_shadows.capacity;
+        let grow_spot_shadows = spot_lights.len() > self.spot_shadows.capacity;
+        if grow_point_shadows {
+            self.point_shadows = PointShadows::new(
                 &self.device,
-                &self.layouts.light_bind_group_layout,
-                vec![bytemuck::cast_slice(&[self.light])],
-                0,
+                &self.layouts,
+                self.config.shadow_map_size,
+                lights.len(),
             );
-            let depth_texture_view =
-                self.shadow_cube_map
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor {
-                        base_array_layer: i as u32,
-                        array_layer_count: Some(1),
-                        label: None,
-                        format: Some(wgpu::TextureFormat::Depth32Float),
-                        dimension: Some(wgpu::TextureViewDimension::D2),
-                        aspect: wgpu::TextureAspect::DepthOnly,
-                        usage: Some(
-                            wgpu::TextureUsages::RENDER_ATTACHMENT
-                                | wgpu::TextureUsages::TEXTURE_BINDING,
-                        ),
-                        ..Default::default()
-                    });
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &depth_texture_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-            render_pass.set_viewport(0.0, 0.0, 1024.0, 1024.0, 0.0, 1.0);
-            render_pass.set_pipeline(&self.shadow_pipeline);
-            render_pass.set_bind_group(
-                self.light_uniform.bind_index,
-                &new_light_uniform.bind_group,
-                &[],
+        }
+        if grow_spot_shadows {
+            self.spot_shadows = SpotShadows::new(
+                &self.device,
+                &self.layouts,
+                self.config.shadow_map_size,
+                spot_lights.len(),
+            );
+        }
+        if grow_point_shadows || grow_spot_shadows {
+            self.shadow_binding = shadow_binding(
+                &self.device,
+                &self.layouts,
+                &self.point_shadows,
+                &self.sun_shadows,
+                &self.spot_shadows,
+                &self.ssao.occlusion,
+            );
+        }
+        let faces = lights.iter().flat_map(|light| {
+            cube_face_view_projs(light).map(|view_proj| Light {
+                view_proj: view_proj.to_cols_array_2d(),
+                ..*light
+            })
+        });
+        for (light, (depth_texture_view, light_uniform)) in
+            faces.zip(self.point_shadows.faces.iter_mut())
+        {
+            // every face has its own buffer, writes are applied before the whole submission
+            light_uniform.update_buffer(&self.queue, vec![bytemuck::cast_slice(&[light])]);
+            State::draw_shadow_casters(
+                encoder,
+                depth_texture_view,
+                &self.shadow_pipeline,
+                &self.cutout_shadow_pipeline,
+                light_uniform,
+                self.config.shadow_map_size,
+                &self.scene,
+                &self.instance_buffers,
+            );
+        }
+
+        for (spot_light, (depth_texture_view, light_uniform)) in
+            spot_lights.iter().zip(self.spot_shadows.layers.iter_mut())
+        {
+            // same linear distance depth as the point lights, through the spot's perspective
+            let light = Light {
+                pos: spot_light.pos,
+                range: spot_light.range,
+                shadow: spot_light.shadow,
+                view_proj: spot_light.view_proj,
+                ..Light::new([0.0; 3], [0.0; 3])
+            };
+            light_uniform.update_buffer(&self.queue, vec![bytemuck::cast_slice(&[light])]);
+            State::draw_shadow_casters(
+                encoder,
+                depth_texture_view,
+                &self.shadow_pipeline,
+                &self.cutout_shadow_pipeline,
+                light_uniform,
+                self.config.shadow_map_size,
+                &self.scene,
+                &self.instance_buffers,
             );
-            for (model, instances) in self.models.iter() {
-                let instance_buffer = Instance::make_buffer(&self.device, &instances);
-                for mesh in model.meshes.iter() {
-                    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-                    render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
-                    render_pass
-                        .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                    render_pass.draw_indexed(
-                        0..mesh.len_indicies as u32,
-                        0,
-                        0..instances.len() as u32,
+        }
+
+        if self.scene.sun.is_none() {
+            return;
+        }
+        let sun = self.sun_raw();
+        let cascades = sun.view_projs.iter().take(sun.cascade_count as usize);
+        for (view_proj, (depth_texture_view, light_uniform)) in
+            cascades.zip(self.sun_shadows.cascades.iter_mut())
+        {
+            let light = Light {
+                view_proj: *view_proj,
+                ..Light::new([0.0; 3], [0.0; 3])
+            };
+            light_uniform.update_buffer(&self.queue, vec![bytemuck::cast_slice(&[light])]);
+            State::draw_shadow_casters(
+                encoder,
+                depth_texture_view,
+                &self.sun_shadow_pipeline,
+                &self.sun_cutout_shadow_pipeline,
+                light_uniform,
+                self.config.shadow_map_size,
+                &self.scene,
+                &self.instance_buffers,
+            );
+        }
+    }
+
+    /// Renders every model into one shadow map layer as seen from `light_uniform`, cutout
+    /// materials with `cutout_pipeline`.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_shadow_casters(
+        encoder: &mut wgpu::CommandEncoder,
+        depth_texture_view: &wgpu::TextureView,
+        pipeline: &wgpu::RenderPipeline,
+        cutout_pipeline: &wgpu::RenderPipeline,
+        light_uniform: &Binding,
+        shadow_map_size: u32,
+        scene: &Scene,
+        instance_buffers: &[InstanceBuffer],
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_texture_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        let shadow_map_size = shadow_map_size as f32;
+        render_pass.set_viewport(0.0, 0.0, shadow_map_size, shadow_map_size, 0.0, 1.0);
+        render_pass.set_bind_group(light_uniform.bind_index, &light_uniform.bind_group, &[]);
+        for ((model, instances), instance_buffer) in scene.models.iter().zip(instance_buffers) {
+            for mesh in model.meshes.iter() {
+                let material = &model.materials[mesh.material_id];
+                if material.alpha_mode == AlphaMode::Mask {
+                    render_pass.set_pipeline(cutout_pipeline);
+                    render_pass.set_bind_group(
+                        material.texture_binding.bind_index,
+                        &material.texture_binding.bind_group,
+                        &[],
+                    );
+                    render_pass.set_bind_group(
+                        material.uniform.bind_index,
+                        &material.uniform.bind_group,
+                        &[],
                     );
+                } else {
+                    render_pass.set_pipeline(pipeline);
                 }
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+                render_pass
+                    .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..mesh.len_indicies as u32, 0, 0..instances.len() as u32);
             }
         }
     }
 
-    pub fn spin_teapots(&mut self) {
-        for instance in self.models[0].1.iter_mut() {
-            *instance = glam::f32::Quat::from_rotation_y(1.0f32.to_radians()) * *instance
-        }
+    /// The shaded spot lights with their shadow view-projections filled in.
+    pub fn spot_lights(&self) -> Vec<SpotLight> {
+        let count = self.scene.spot_lights.len().min(MAX_SPOT_LIGHTS);
+        return self.scene.spot_lights[..count]
+            .iter()
+            .map(|light| SpotLight {
+                view_proj: spot_view_proj(light).to_cols_array_2d(),
+                ..*light
+            })
+            .collect();
     }
 
-    pub fn render(&mut self) {
-        let surface_texutre = self.surface.get_current_texture().unwrap();
-        let texture_view = surface_texutre
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor {
-                format: Some(self.surface_format.add_srgb_suffix()),
-                ..Default::default()
-            });
+    /// Directional light uniform with its cascades fit to the current camera.
+    pub fn sun_raw(&self) -> SunRaw {
+        let mut cascades = self.config.cascades;
+        cascades.max_distance = cascades.max_distance.min(CAMERA_FAR_PLANE);
+        return sun_raw(
+            self.scene.sun.as_ref(),
+            &self.camera,
+            CAMERA_NEAR_PLANE,
+            &cascades,
+            self.config.shadow_map_size,
+        );
+    }
+
+    /// Draws a frame. A lost or outdated surface is reconfigured and the frame is skipped.
+    pub fn render(&mut self) -> Result<()> {
+        if self.is_minimized() {
+            return Ok(());
+        }
         let mut encoder = self.device.create_command_encoder(&Default::default());
-        self.shadow_pass(&mut encoder);
+        match &self.target {
+            Target::Window { window, surface } => {
+                let window = window.clone();
+                let surface_texutre = match surface.get_current_texture() {
+                    Ok(surface_texture) => surface_texture,
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        self.configure_surface();
+                        return Ok(());
+                    }
+                    Err(wgpu::SurfaceError::Timeout) => return Ok(()),
+                    Err(err) => return Err(err.into()),
+                };
+                let suboptimal = surface_texutre.suboptimal;
+                let texture_view =
+                    surface_texutre
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor {
+                            format: Some(self.surface_format.add_srgb_suffix()),
+                            ..Default::default()
+                        });
+                self.draw_frame(&mut encoder, &texture_view);
+                self.queue.submit([encoder.finish()]);
+                window.pre_present_notify();
+                surface_texutre.present();
+                if suboptimal {
+                    self.configure_surface();
+                }
+            }
+            Target::Offscreen(offscreen) => {
+                let offscreen = offscreen.clone();
+                self.draw_frame(&mut encoder, &offscreen.texture_view);
+                offscreen.copy_to_buffer(&mut encoder);
+                self.queue.submit([encoder.finish()]);
+            }
+        }
+        return Ok(());
+    }
+
+    /// Renders a frame offscreen and reads it back. Panics when the state draws to a window.
+    pub fn render_to_image(&mut self) -> Result<image::RgbaImage> {
+        self.render()?;
+        let Target::Offscreen(offscreen) = &self.target else {
+            panic!("render_to_image requires a headless state");
+        };
+        return offscreen.read_image(&self.device);
+    }
+
+    pub fn request_redraw(&self) {
+        if let Target::Window { window, .. } = &self.target {
+            window.request_redraw();
+        }
+    }
+
+    fn draw_frame(&mut self, encoder: &mut wgpu::CommandEncoder, texture_view: &wgpu::TextureView) {
+        self.update_instance_buffers();
+        self.shadow_pass(encoder);
         self.globals_uniform.update_buffer(
             &self.queue,
             vec![
                 bytemuck::cast_slice(&[self.camera.to_camera_raw()]),
-                bytemuck::cast_slice(&[self.light]),
+                bytemuck::cast_slice(&[LightsRaw::new(&self.scene.lights)]),
+                bytemuck::cast_slice(&[self.sun_raw()]),
+                bytemuck::cast_slice(&[SpotLightsRaw::new(&self.spot_lights())]),
+                bytemuck::cast_slice(&[ShadowParamsRaw::new(&self.config.shadows)]),
             ],
         );
+        if self.config.ssao.enabled {
+            self.ssao.draw_normals(
+                encoder,
+                &self.depth_texture,
+                &self.globals_uniform,
+                &self.scene,
+                &self.instance_buffers,
+            );
+            self.ssao
+                .apply(encoder, &self.queue, &self.camera, &self.config.ssao);
+            if self.config.ssao.debug {
+                self.ssao.draw_debug(encoder, texture_view);
+                return;
+            }
+        } else {
+            self.ssao.clear(encoder);
+        }
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: None,
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &texture_view,
+                view: &self.hdr_texture.texture_view,
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -534,11 +1028,35 @@ impl State {
         );
         render_pass.set_pipeline(&self.light_pipeline);
         self.draw_light(&mut render_pass);
-        render_pass.set_pipeline(&self.render_pipeline);
         self.draw_model_instanced(&mut render_pass);
+        self.draw_transparent(&mut render_pass);
         drop(render_pass);
-        self.queue.submit([encoder.finish()]);
-        self.window.pre_present_notify();
-        surface_texutre.present();
+
+        if self.config.bloom.enabled {
+            self.bloom
+                .apply(encoder, &self.queue, &self.hdr_texture, &self.config.bloom);
+        }
+
+        let tonemapping = self.config.tonemapping;
+        let auto_exposure = match &mut self.auto_exposure {
+            Some(auto_exposure) if tonemapping.auto_exposure => {
+                auto_exposure.update(
+                    encoder,
+                    &self.queue,
+                    &self.hdr_texture,
+                    &tonemapping,
+                    &self.tonemap,
+                );
+                true
+            }
+            _ => false,
+        };
+        self.tonemap.draw(
+            encoder,
+            &self.queue,
+            texture_view,
+            &tonemapping,
+            auto_exposure,
+        );
     }
 }
@@ -1,6 +1,75 @@
+use std::path::Path;
+
 use winit::dpi::PhysicalSize;
 
+use crate::error::{Error, Result};
+use crate::mipmap::{self, MipmapGenerator};
+
+/// How a loaded texture is sampled, set from MTL texture options or glTF samplers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SamplerOptions {
+    pub address_mode_u: wgpu::AddressMode,
+    pub address_mode_v: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    /// Filter between mip levels, only used with `mipmaps`.
+    pub mipmap_filter: wgpu::FilterMode,
+    /// Generates a full mip chain when the texture is loaded.
+    pub mipmaps: bool,
+    /// Anisotropic filtering up to this many samples when every filter is linear, wgpu drops
+    /// it to 1 on adapters that do not support it.
+    pub anisotropy: u16,
+}
+
+impl Default for SamplerOptions {
+    fn default() -> Self {
+        return SamplerOptions {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            mipmaps: true,
+            anisotropy: 16,
+        };
+    }
+}
+
+impl SamplerOptions {
+    /// Clamps both texture coordinates to the edge instead of repeating.
+    pub fn clamped() -> SamplerOptions {
+        return SamplerOptions {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        };
+    }
 
+    fn create_sampler(&self, device: &wgpu::Device) -> wgpu::Sampler {
+        let linear = [self.mag_filter, self.min_filter, self.mipmap_filter]
+            .iter()
+            .all(|filter| *filter == wgpu::FilterMode::Linear);
+        return device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: self.address_mode_u,
+            address_mode_v: self.address_mode_v,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            // only the first level is sampled without mipmaps
+            lod_max_clamp: if self.mipmaps { 32.0 } else { 0.0 },
+            anisotropy_clamp: if linear && self.mipmaps {
+                self.anisotropy.max(1)
+            } else {
+                1
+            },
+            ..Default::default()
+        });
+    }
+}
+
+#[derive(Clone)]
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub texture_view: wgpu::TextureView,
@@ -52,28 +121,153 @@ impl Texture {
         };
     }
 
-    pub fn create_cube_depth_texture(device: &wgpu::Device, size: PhysicalSize<u32>) -> Texture{
-        let mut cube_depth_texture = Texture::create_depth_texture(device, size, 6);
+    /// Depth cube map array with `cubes` cube maps, viewed as a single `CubeArray`.
+    pub fn create_cube_array_depth_texture(
+        device: &wgpu::Device,
+        size: PhysicalSize<u32>,
+        cubes: u32,
+    ) -> Texture {
+        let mut cube_depth_texture = Texture::create_depth_texture(device, size, 6 * cubes);
         cube_depth_texture.texture_view =
             cube_depth_texture
                 .texture
                 .create_view(&wgpu::TextureViewDescriptor {
                     format: Some(cube_depth_texture.texture.format()),
-                    dimension: Some(wgpu::TextureViewDimension::Cube),
+                    dimension: Some(wgpu::TextureViewDimension::CubeArray),
                     aspect: wgpu::TextureAspect::DepthOnly,
                     base_array_layer: 0,
-                    array_layer_count: Some(6),
+                    array_layer_count: Some(6 * cubes),
                     ..Default::default()
                 });
         return cube_depth_texture;
     }
 
+    /// Depth texture with `layers` layers, viewed as a single `D2Array`.
+    pub fn create_array_depth_texture(
+        device: &wgpu::Device,
+        size: PhysicalSize<u32>,
+        layers: u32,
+    ) -> Texture {
+        let mut array_depth_texture = Texture::create_depth_texture(device, size, layers);
+        array_depth_texture.texture_view =
+            array_depth_texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor {
+                    format: Some(array_depth_texture.texture.format()),
+                    dimension: Some(wgpu::TextureViewDimension::D2Array),
+                    aspect: wgpu::TextureAspect::DepthOnly,
+                    base_array_layer: 0,
+                    array_layer_count: Some(layers),
+                    ..Default::default()
+                });
+        return array_depth_texture;
+    }
+
+    /// Color texture that can be rendered to, sampled and copied, like an HDR scene target.
+    pub fn create_render_target(
+        device: &wgpu::Device,
+        size: PhysicalSize<u32>,
+        format: wgpu::TextureFormat,
+    ) -> Texture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: size.width.max(1),
+                height: size.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        return Texture {
+            texture,
+            texture_view,
+            sampler,
+        };
+    }
+
     pub fn load_texture(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        image_rgba: &image::RgbaImage,
+    ) -> Texture {
+        return Texture::load_texture_with_format(
+            device,
+            queue,
+            mipmaps,
+            image_rgba,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+        );
+    }
+
+    /// Like `load_texture`, `Rgba8Unorm` keeps data such as roughness from being decoded as
+    /// sRGB colour.
+    pub fn load_texture_with_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        image_rgba: &image::RgbaImage,
+        format: wgpu::TextureFormat,
+    ) -> Texture {
+        let mut encoder = device.create_command_encoder(&Default::default());
+        let texture = Texture::load_texture_with_options(
+            device,
+            queue,
+            &mut encoder,
+            mipmaps,
+            image_rgba,
+            format,
+            &SamplerOptions::default(),
+        );
+        queue.submit([encoder.finish()]);
+        return texture;
+    }
+
+    /// Uploads an image sampled as `options` say. The passes generating its mip chain are
+    /// recorded into `encoder`, so loads can share one submit.
+    pub fn load_texture_with_options(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        mipmaps: &MipmapGenerator,
         image_rgba: &image::RgbaImage,
+        format: wgpu::TextureFormat,
+        options: &SamplerOptions,
+    ) -> Texture {
+        let texture = Texture::upload(device, queue, image_rgba, format, options);
+        mipmaps.generate(device, encoder, &texture.texture);
+        return texture;
+    }
+
+    /// Creates the texture with room for its mip chain and writes the first level.
+    fn upload(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image_rgba: &image::RgbaImage,
+        format: wgpu::TextureFormat,
+        options: &SamplerOptions,
     ) -> Texture {
         let dimensions = image_rgba.dimensions();
+        let mip_level_count = match options.mipmaps {
+            true => mipmap::mip_level_count(dimensions.0, dimensions.1),
+            false => 1,
+        };
 
         let texture_size = wgpu::Extent3d {
             width: dimensions.0,
@@ -81,14 +275,19 @@ impl Texture {
             depth_or_array_layers: 1,
         };
 
+        let mut usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+        if mip_level_count > 1 {
+            // the lower mip levels are rendered from the first
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: None,
             size: texture_size,
-            mip_level_count: 1,
+            mip_level_count,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            format,
+            usage,
             view_formats: &[],
         });
         queue.write_texture(
@@ -98,7 +297,7 @@ impl Texture {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            &image_rgba,
+            image_rgba,
             wgpu::TexelCopyBufferLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * dimensions.0),
@@ -107,16 +306,7 @@ impl Texture {
             texture_size,
         );
         let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: None,
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+        let sampler = options.create_sampler(device);
         return Texture {
             texture,
             texture_view,
@@ -124,13 +314,97 @@ impl Texture {
         };
     }
 
+    pub fn open(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        path: impl AsRef<Path>,
+    ) -> Result<Texture> {
+        return Texture::open_with_format(
+            device,
+            queue,
+            mipmaps,
+            path,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+        );
+    }
+
+    pub fn open_with_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &MipmapGenerator,
+        path: impl AsRef<Path>,
+        format: wgpu::TextureFormat,
+    ) -> Result<Texture> {
+        let image_rgba = Texture::open_image(path)?;
+        return Ok(Texture::load_texture_with_format(
+            device,
+            queue,
+            mipmaps,
+            &image_rgba,
+            format,
+        ));
+    }
+
+    pub fn open_with_options(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        mipmaps: &MipmapGenerator,
+        path: impl AsRef<Path>,
+        format: wgpu::TextureFormat,
+        options: &SamplerOptions,
+    ) -> Result<Texture> {
+        let image_rgba = Texture::open_image(path)?;
+        return Ok(Texture::load_texture_with_options(
+            device,
+            queue,
+            encoder,
+            mipmaps,
+            &image_rgba,
+            format,
+            options,
+        ));
+    }
+
+    /// Decodes an image file to 8 bit RGBA without uploading it.
+    pub fn open_image(path: impl AsRef<Path>) -> Result<image::RgbaImage> {
+        let path = path.as_ref();
+        return Ok(image::open(path)
+            .map_err(|source| Error::Image {
+                path: path.to_owned(),
+                source,
+            })?
+            .to_rgba8());
+    }
+
     pub fn create_solid_color_texture(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         rgba: [u8; 4],
     ) -> Texture {
-        let image_rgba = image::RgbaImage::from_fn(8, 8, |_, _| image::Rgba(rgba));
-        return Texture::load_texture(device, queue, &image_rgba);
+        return Texture::create_solid_color_texture_with_format(
+            device,
+            queue,
+            rgba,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+        );
     }
-}
 
+    pub fn create_solid_color_texture_with_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rgba: [u8; 4],
+        format: wgpu::TextureFormat,
+    ) -> Texture {
+        let image_rgba = image::RgbaImage::from_pixel(1, 1, image::Rgba(rgba));
+        // a single texel has no mip chain to generate
+        return Texture::upload(
+            device,
+            queue,
+            &image_rgba,
+            format,
+            &SamplerOptions::default(),
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use wgpu::util::DeviceExt;
+
+use crate::texture::Texture;
+
+/// Format the scene is lit and shaded in before it is tonemapped for display.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Curve mapping HDR colours into the displayable range.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ToneMapper {
+    /// Clips everything above 1, what rendering straight to the surface used to do.
+    #[default]
+    Linear,
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+    /// Troy Sobotka's AgX, desaturates bright colours instead of skewing their hue.
+    Agx,
+}
+
+impl ToneMapper {
+    /// The next operator, wrapping around, for cycling through them at runtime.
+    pub fn next(self) -> ToneMapper {
+        return match self {
+            ToneMapper::Linear => ToneMapper::Reinhard,
+            ToneMapper::Reinhard => ToneMapper::Aces,
+            ToneMapper::Aces => ToneMapper::Agx,
+            ToneMapper::Agx => ToneMapper::Linear,
+        };
+    }
+}
+
+/// Tonemapping and exposure settings, they can be changed between frames.
+#[derive(Copy, Clone, Debug)]
+pub struct ToneMapConfig {
+    pub operator: ToneMapper,
+    /// Factor colours are scaled by before tonemapping. With `auto_exposure` it is applied on
+    /// top of the measured exposure, as a compensation.
+    pub exposure: f32,
+    /// Expose for the average scene luminance, measured with a histogram every frame. Needs
+    /// compute shader support, adapters without it keep the manual exposure.
+    pub auto_exposure: bool,
+    /// Luminance range the histogram covers, in stops (log2 of the luminance).
+    pub min_log_luminance: f32,
+    pub max_log_luminance: f32,
+    /// How quickly auto exposure follows changes in brightness, per second.
+    pub adaptation_rate: f32,
+}
+
+impl Default for ToneMapConfig {
+    fn default() -> Self {
+        return ToneMapConfig {
+            operator: ToneMapper::Linear,
+            exposure: 1.0,
+            auto_exposure: false,
+            min_log_luminance: -8.0,
+            max_log_luminance: 4.0,
+            adaptation_rate: 1.5,
+        };
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TonemapParams {
+    tone_mapper: u32,
+    exposure: f32,
+    auto_exposure: u32,
+    _padding: u32,
+}
+
+/// Fullscreen pass tonemapping the HDR scene into the display target.
+pub struct Tonemap {
+    pub pipeline: wgpu::RenderPipeline,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    pub params_buffer: wgpu::Buffer,
+    /// Exposure measured by `AutoExposure`, copied in every frame it runs.
+    pub exposure_buffer: wgpu::Buffer,
+}
+
+impl Tonemap {
+    pub fn new(device: &wgpu::Device, hdr: &Texture, output_format: wgpu::TextureFormat) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<TonemapParams>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(16),
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<TonemapParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let exposure_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&[1.0f32, 1.0, 0.0, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = Tonemap::create_bind_group(
+            device,
+            &bind_group_layout,
+            hdr,
+            &params_buffer,
+            &exposure_buffer,
+        );
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("./shaders/tonemap.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(output_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        return Tonemap {
+            pipeline,
+            bind_group_layout,
+            bind_group,
+            params_buffer,
+            exposure_buffer,
+        };
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        hdr: &Texture,
+        params_buffer: &wgpu::Buffer,
+        exposure_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        return device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&hdr.texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: exposure_buffer.as_entire_binding(),
+                },
+            ],
+        });
+    }
+
+    /// Points the pass at a new HDR texture, after the render target was resized.
+    pub fn set_input(&mut self, device: &wgpu::Device, hdr: &Texture) {
+        self.bind_group = Tonemap::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            hdr,
+            &self.params_buffer,
+            &self.exposure_buffer,
+        );
+    }
+
+    /// Tonemaps the HDR input into `output`. `auto_exposure` tells whether `exposure_buffer`
+    /// holds a measured exposure this frame.
+    pub fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        output: &wgpu::TextureView,
+        config: &ToneMapConfig,
+        auto_exposure: bool,
+    ) {
+        let params = TonemapParams {
+            tone_mapper: config.operator as u32,
+            exposure: config.exposure,
+            auto_exposure: auto_exposure as u32,
+            _padding: 0,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        // a single triangle covering the whole target
+        render_pass.draw(0..3, 0..1);
+    }
+}
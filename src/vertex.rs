@@ -6,6 +6,9 @@ pub struct Vertex {
     pub pos: [f32; 3],
     pub tex_cords: [f32; 2],
     pub normals: [f32; 3],
+    /// Direction of increasing u, with the bitangent `w * cross(normal, tangent)` pointing up
+    /// the texture, towards decreasing v, as in glTF.
+    pub tangent: [f32; 4],
 }
 
 impl Vertex {
@@ -29,6 +32,12 @@ impl Vertex {
                     offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
                     shader_location: 2,
                 },
+                // locations 3 to 6 hold the instance transform
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                },
             ],
         };
     }
@@ -53,6 +62,82 @@ impl Vertex {
     }
 }
 
+/// Sets every vertex normal to the area weighted average of the faces sharing it.
+pub fn generate_smooth_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut normals = vec![glam::Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| i as usize);
+        let pos = |i: usize| glam::Vec3::from_array(vertices[i].pos);
+        // the cross product length is twice the triangle area, so bigger faces weigh more
+        let face_normal = (pos(b) - pos(a)).cross(pos(c) - pos(a));
+        normals[a] += face_normal;
+        normals[b] += face_normal;
+        normals[c] += face_normal;
+    }
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        vertex.normals = normal.normalize_or_zero().into();
+    }
+}
+
+/// Gives every triangle its own three vertices carrying the face normal.
+pub fn generate_flat_normals(vertices: &[Vertex], indices: &[u32]) -> (Vec<Vertex>, Vec<u32>) {
+    let mut flat_vertices: Vec<Vertex> = Vec::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        let corners = [triangle[0], triangle[1], triangle[2]].map(|i| vertices[i as usize]);
+        let [a, b, c] = corners.map(|vertex| glam::Vec3::from_array(vertex.pos));
+        let face_normal = (b - a).cross(c - a).normalize_or_zero();
+        flat_vertices.extend(corners.map(|vertex| Vertex {
+            normals: face_normal.into(),
+            ..vertex
+        }));
+    }
+    let flat_indices = (0..flat_vertices.len() as u32).collect();
+    return (flat_vertices, flat_indices);
+}
+
+/// Sets every vertex tangent from the texture coordinates of the triangles sharing it, following
+/// the MikkTSpace conventions normal maps are baked with: faces are weighted by their corner
+/// angle, the tangent is made orthogonal to the vertex normal and `w` holds the handedness of
+/// the uv mapping. Normals have to be set before.
+pub fn generate_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut tangents = vec![glam::Vec3::ZERO; vertices.len()];
+    let mut bitangents = vec![glam::Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let corners = [triangle[0], triangle[1], triangle[2]].map(|i| i as usize);
+        let [pos_a, pos_b, pos_c] = corners.map(|i| glam::Vec3::from_array(vertices[i].pos));
+        let [uv_a, uv_b, uv_c] = corners.map(|i| glam::Vec2::from_array(vertices[i].tex_cords));
+        let (edge_1, edge_2) = (pos_b - pos_a, pos_c - pos_a);
+        let (delta_1, delta_2) = (uv_b - uv_a, uv_c - uv_a);
+        let determinant = delta_1.x * delta_2.y - delta_2.x * delta_1.y;
+        // degenerate uv mapping, nothing to derive a direction from
+        if determinant.abs() < f32::EPSILON {
+            continue;
+        }
+        let tangent = (edge_1 * delta_2.y - edge_2 * delta_1.y) / determinant;
+        // texture coordinates start at the top, so up the texture is decreasing v
+        let bitangent = (edge_1 * delta_2.x - edge_2 * delta_1.x) / determinant;
+        let positions = [pos_a, pos_b, pos_c];
+        for (k, &i) in corners.iter().enumerate() {
+            let to_next = positions[(k + 1) % 3] - positions[k];
+            let to_previous = positions[(k + 2) % 3] - positions[k];
+            let angle = to_next.angle_between(to_previous);
+            if angle.is_finite() {
+                tangents[i] += tangent.normalize_or_zero() * angle;
+                bitangents[i] += bitangent.normalize_or_zero() * angle;
+            }
+        }
+    }
+    for ((vertex, tangent), bitangent) in vertices.iter_mut().zip(tangents).zip(bitangents) {
+        let normal = glam::Vec3::from_array(vertex.normals);
+        let tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+        let handedness = match normal.cross(tangent).dot(bitangent) < 0.0 {
+            true => -1.0,
+            false => 1.0,
+        };
+        vertex.tangent = tangent.extend(handedness).into();
+    }
+}
+
 impl std::ops::Add<glam::f32::Vec3> for Vertex {
     type Output = Self;
 
@@ -62,7 +147,7 @@ impl std::ops::Add<glam::f32::Vec3> for Vertex {
             pos: new_pos.into(),
             tex_cords:self.tex_cords,
             normals: self.normals,
-            
+            tangent: self.tangent,
         }
     }
 }
@@ -73,126 +158,150 @@ pub const CUBE_VERTICES: &[Vertex] = &[
         pos: [-1.0, -1.0, 1.0],
         tex_cords: [0.3, 0.3],
         normals: [0.0, 1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [1.0, -1.0, 1.0],
         tex_cords: [0.0, 0.5],
         normals: [0.0, 1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [1.0, 1.0, 1.0],
         tex_cords: [0.2, 0.1],
         normals: [0.0, 1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [-1.0, 1.0, 1.0],
         tex_cords: [0.0, 0.1],
         normals: [0.0, 1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     // bot
     Vertex {
         pos: [-1.0, 1.0, -1.0],
         tex_cords: [0.8, 0.2],
         normals: [0.0, -1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [1.0, 1.0, -1.0],
         tex_cords: [0.0, 0.2],
         normals: [0.0, -1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [1.0, -1.0, -1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, -1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [-1.0, -1.0, -1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, -1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     // right
     Vertex {
         pos: [1.0, -1.0, -1.0],
         tex_cords: [0.0, 0.8],
         normals: [1.0, 0.0, 0.0],
+        tangent: [0.0, 0.0, 1.0, 1.0],
     },
     Vertex {
         pos: [1.0, 1.0, -1.0],
         tex_cords: [0.9, 0.0],
         normals: [1.0, 0.0, 0.0],
+        tangent: [0.0, 0.0, 1.0, 1.0],
     },
     Vertex {
         pos: [1.0, 1.0, 1.0],
         tex_cords: [0.2, 0.1],
         normals: [1.0, 0.0, 0.0],
+        tangent: [0.0, 0.0, 1.0, 1.0],
     },
     Vertex {
         pos: [1.0, -1.0, 1.0],
         tex_cords: [0.2, 0.1],
         normals: [1.0, 0.0, 0.0],
+        tangent: [0.0, 0.0, 1.0, 1.0],
     },
     //left
     Vertex {
         pos: [-1.0, -1.0, 1.0],
         tex_cords: [0.2, 0.1],
         normals: [-1.0, 0.0, 0.0],
+        tangent: [0.0, 0.0, 1.0, 1.0],
     },
     Vertex {
         pos: [-1.0, 1.0, 1.0],
         tex_cords: [0.2, 0.0],
         normals: [-1.0, 0.0, 0.0],
+        tangent: [0.0, 0.0, 1.0, 1.0],
     },
     Vertex {
         pos: [-1.0, 1.0, -1.0],
         tex_cords: [0.0, 0.0],
         normals: [-1.0, 0.0, 0.0],
+        tangent: [0.0, 0.0, 1.0, 1.0],
     },
     Vertex {
         pos: [-1.0, -1.0, -1.0],
         tex_cords: [0.0, 0.0],
         normals: [-1.0, 0.0, 0.0],
+        tangent: [0.0, 0.0, 1.0, 1.0],
     },
     //front
     Vertex {
         pos: [1.0, 1.0, -1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [-1.0, 1.0, -1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [-1.0, 1.0, 1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [1.0, 1.0, 1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     //back
     Vertex {
         pos: [1.0, -1.0, 1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 0.0, -1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [-1.0, -1.0, 1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 0.0, -1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [-1.0, -1.0, -1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 0.0, -1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [1.0, -1.0, -1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 0.0, -1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
 ];
 pub const CUBE_INDICES: &[u32] = &[
@@ -209,22 +318,95 @@ pub const PLANE_VERTICIES: &[Vertex] = &[
         pos: [1.0, 0.0, -1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [-1.0, 0.0, -1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [-1.0, 0.0, 1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
     Vertex {
         pos: [1.0, 0.0, 1.0],
         tex_cords: [0.0, 0.0],
         normals: [0.0, 1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
     },
 ];
 
 pub const PLANE_INDICIES: &[u32] = &[0, 1, 2, 2, 3, 0];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(pos: [f32; 3]) -> Vertex {
+        return Vertex {
+            pos,
+            tex_cords: [0.0, 0.0],
+            normals: [0.0, 0.0, 0.0],
+            tangent: [0.0, 0.0, 0.0, 0.0],
+        };
+    }
+
+    // two triangles folded 90 degrees along the x axis, one facing +y and one facing +z
+    fn folded_quad() -> (Vec<Vertex>, Vec<u32>) {
+        let vertices = vec![
+            vertex([0.0, 0.0, 0.0]),
+            vertex([1.0, 0.0, 0.0]),
+            vertex([0.0, 0.0, -1.0]),
+            vertex([0.0, 1.0, 0.0]),
+        ];
+        return (vertices, vec![0, 1, 2, 0, 1, 3]);
+    }
+
+    #[test]
+    fn smooth_normals_average_shared_vertices() {
+        let (mut vertices, indices) = folded_quad();
+        generate_smooth_normals(&mut vertices, &indices);
+        let shared = glam::Vec3::from_array(vertices[0].normals);
+        assert!(shared.abs_diff_eq(glam::vec3(0.0, 1.0, 1.0).normalize(), 1e-5));
+        assert_eq!(vertices[2].normals, [0.0, 1.0, 0.0]);
+        assert_eq!(vertices[3].normals, [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn flat_normals_split_vertices_per_face() {
+        let (vertices, indices) = folded_quad();
+        let (flat_vertices, flat_indices) = generate_flat_normals(&vertices, &indices);
+        assert_eq!(flat_vertices.len(), 6);
+        assert_eq!(flat_indices, vec![0, 1, 2, 3, 4, 5]);
+        assert!(flat_vertices[..3]
+            .iter()
+            .all(|v| v.normals == [0.0, 1.0, 0.0]));
+        assert!(flat_vertices[3..]
+            .iter()
+            .all(|v| v.normals == [0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn tangents_follow_texture_coordinates() {
+        // quad in the xy plane facing +z, u along -x and v along -y, so the uvs are mirrored
+        let mut vertices = [
+            ([0.0, 0.0, 0.0], [0.0, 1.0]),
+            ([-1.0, 0.0, 0.0], [1.0, 1.0]),
+            ([-1.0, 1.0, 0.0], [1.0, 0.0]),
+            ([0.0, 1.0, 0.0], [0.0, 0.0]),
+        ]
+        .map(|(pos, tex_cords)| Vertex {
+            tex_cords,
+            normals: [0.0, 0.0, 1.0],
+            ..vertex(pos)
+        });
+        generate_tangents(&mut vertices, &[0, 2, 1, 0, 3, 2]);
+        for vertex in vertices {
+            assert_eq!(vertex.tangent, [-1.0, 0.0, 0.0, -1.0]);
+        }
+    }
+}
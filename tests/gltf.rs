@@ -0,0 +1,262 @@
+//! glTF loader tests, the fixtures are assembled by each test and written to the cargo tmp dir.
+
+use std::path::PathBuf;
+
+use winit::dpi::PhysicalSize;
+
+use spinny::gltf_loader::{load_gltf, load_gltf_slice};
+use spinny::{AlphaMode, Error, ImportOptions, Instance, Model, RenderConfig, Shading, State};
+
+fn headless_state() -> Option<State> {
+    let state = pollster::block_on(State::new_headless(
+        PhysicalSize::new(16, 16),
+        RenderConfig {
+            force_fallback_adapter: true,
+            ..Default::default()
+        },
+    ));
+    return match state {
+        Ok(state) => Some(state),
+        Err(Error::NoAdapter) => {
+            eprintln!("no software adapter available, skipping gltf test");
+            None
+        }
+        Err(err) => panic!("{err}"),
+    };
+}
+
+/// One triangle in the xy plane, without normals or texture coordinates.
+const POSITIONS: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+const INDICES: [u16; 3] = [0, 1, 2];
+
+/// Positions followed by the indices, padded to a multiple of four bytes.
+fn buffer_bytes() -> Vec<u8> {
+    let mut bytes = bytemuck::cast_slice::<_, u8>(&POSITIONS).to_vec();
+    bytes.extend_from_slice(bytemuck::cast_slice(&INDICES));
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+    return bytes;
+}
+
+/// A 2x1 png, red then green.
+fn png_bytes() -> Vec<u8> {
+    let image = image::RgbaImage::from_fn(2, 1, |x, _| match x {
+        0 => image::Rgba([255, 0, 0, 255]),
+        _ => image::Rgba([0, 255, 0, 255]),
+    });
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+    return bytes.into_inner();
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let triple = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, triple[0], triple[1], triple[2]]);
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    return encoded;
+}
+
+/// glTF json with a red tinted, textured and cut out metallic-roughness triangle, its texture
+/// sampled without mipmaps, drawn by two nodes: a translated parent with a scaled child, and
+/// a second root node.
+fn document(buffer_uri: Option<&str>, image: &str) -> String {
+    let buffer_len = buffer_bytes().len();
+    let buffer_uri = buffer_uri.map_or(String::new(), |uri| format!(r#""uri": "{uri}","#));
+    return format!(
+        r#"{{
+            "asset": {{ "version": "2.0" }},
+            "scene": 0,
+            "scenes": [{{ "nodes": [0, 2] }}],
+            "nodes": [
+                {{ "translation": [1.0, 0.0, 0.0], "children": [1] }},
+                {{ "scale": [2.0, 2.0, 2.0], "mesh": 0 }},
+                {{ "translation": [0.0, 0.0, -3.0], "mesh": 0 }}
+            ],
+            "meshes": [{{
+                "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "material": 0 }}]
+            }}],
+            "materials": [{{
+                "pbrMetallicRoughness": {{
+                    "baseColorFactor": [1.0, 0.0, 0.0, 1.0],
+                    "baseColorTexture": {{ "index": 0 }},
+                    "metallicFactor": 0.25,
+                    "roughnessFactor": 0.5
+                }},
+                "emissiveFactor": [0.0, 0.5, 0.0],
+                "normalTexture": {{ "index": 0, "scale": 0.5 }},
+                "occlusionTexture": {{ "index": 0 }},
+                "alphaMode": "MASK",
+                "alphaCutoff": 0.25
+            }}],
+            "textures": [{{ "source": 0, "sampler": 0 }}],
+            "samplers": [{{ "magFilter": 9728, "minFilter": 9729, "wrapS": 33071 }}],
+            "images": [{{ {image} }}],
+            "accessors": [
+                {{
+                    "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                    "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+                }},
+                {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+            ],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+            ],
+            "buffers": [{{ {buffer_uri} "byteLength": {buffer_len} }}]
+        }}"#
+    );
+}
+
+fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+    let mut json = json.as_bytes().to_vec();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    let length = 12 + 8 + json.len() + 8 + bin.len();
+    let mut glb = Vec::new();
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json);
+    glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(bin);
+    return glb;
+}
+
+fn check_triangle(models: &[(Model, Vec<Instance>)]) {
+    assert_eq!(models.len(), 1);
+    let (model, instances) = &models[0];
+    assert_eq!(model.meshes.len(), 1);
+    assert_eq!(model.meshes[0].len_indicies, 3);
+    assert_eq!(model.materials.len(), 1);
+    let material = &model.materials[model.meshes[0].material_id];
+    assert_eq!(material.shading, Shading::Pbr);
+    assert_eq!(material.params.base_color, [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(material.params.metallic, 0.25);
+    assert_eq!(material.params.roughness, 0.5);
+    assert_eq!(material.params.emissive, [0.0, 0.5, 0.0]);
+    assert_eq!(material.params.normal_scale, 0.5);
+    assert_eq!(material.alpha_mode, AlphaMode::Mask);
+    assert_eq!(material.params.alpha_cutoff, 0.25);
+    let size = material.textures.diffuse.texture.size();
+    assert_eq!((size.width, size.height), (2, 1));
+    // the sampler's minification filter does not use mipmaps
+    assert_eq!(material.textures.diffuse.texture.mip_level_count(), 1);
+    // the occlusion map reuses the normal map's upload, the sRGB base colour needs its own
+    assert_eq!(
+        material.textures.occlusion.texture,
+        material.textures.normal.texture
+    );
+    assert_ne!(
+        material.textures.diffuse.texture,
+        material.textures.normal.texture
+    );
+
+    let child = glam::Mat4::from_translation(glam::vec3(1.0, 0.0, 0.0))
+        * glam::Mat4::from_scale(glam::Vec3::splat(2.0));
+    let second = glam::Mat4::from_translation(glam::vec3(0.0, 0.0, -3.0));
+    let transforms = instances
+        .iter()
+        .map(|instance| glam::Mat4::from_cols_array_2d(&instance.transform))
+        .collect::<Vec<_>>();
+    assert_eq!(transforms, vec![child, second]);
+}
+
+#[test]
+fn loads_embedded_gltf() {
+    let Some(state) = headless_state() else {
+        return;
+    };
+    let json = document(
+        Some(&format!(
+            "data:application/octet-stream;base64,{}",
+            base64(&buffer_bytes())
+        )),
+        &format!(r#""uri": "data:image/png;base64,{}""#, base64(&png_bytes())),
+    );
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("gltf-embedded");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("triangle.gltf"), json).unwrap();
+    let models = load_gltf(
+        dir.join("triangle.gltf"),
+        &state.device,
+        &state.queue,
+        &state.layouts,
+        &state.mipmaps,
+        &ImportOptions::default(),
+    )
+    .unwrap();
+    check_triangle(&models);
+}
+
+#[test]
+fn loads_external_buffers_and_images() {
+    let Some(state) = headless_state() else {
+        return;
+    };
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("gltf-external");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("triangle.bin"), buffer_bytes()).unwrap();
+    std::fs::write(dir.join("triangle.png"), png_bytes()).unwrap();
+    let json = document(Some("triangle.bin"), r#""uri": "triangle.png""#);
+    std::fs::write(dir.join("triangle.gltf"), json).unwrap();
+    let models = load_gltf(
+        dir.join("triangle.gltf"),
+        &state.device,
+        &state.queue,
+        &state.layouts,
+        &state.mipmaps,
+        &ImportOptions::default(),
+    )
+    .unwrap();
+    check_triangle(&models);
+}
+
+#[test]
+fn loads_binary_gltf() {
+    let Some(state) = headless_state() else {
+        return;
+    };
+    // the png goes into the binary chunk after the geometry, referenced through a buffer view
+    let mut bin = buffer_bytes();
+    let png = png_bytes();
+    let png_offset = bin.len();
+    bin.extend_from_slice(&png);
+    bin.resize(bin.len().next_multiple_of(4), 0);
+    let json = document(None, r#""bufferView": 2, "mimeType": "image/png""#)
+        .replace(
+            r#"{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }"#,
+            &format!(
+                r#"{{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }},
+                {{ "buffer": 0, "byteOffset": {png_offset}, "byteLength": {} }}"#,
+                png.len()
+            ),
+        )
+        .replace(
+            &format!(r#""byteLength": {} }}]"#, buffer_bytes().len()),
+            &format!(r#""byteLength": {} }}]"#, bin.len()),
+        );
+    let models = load_gltf_slice(
+        &glb(&json, &bin),
+        &state.device,
+        &state.queue,
+        &state.layouts,
+        &state.mipmaps,
+        &ImportOptions::default(),
+    )
+    .unwrap();
+    check_triangle(&models);
+}
@@ -0,0 +1,833 @@
+//! Golden-image tests for the shadow, main and light pipelines and the post-processing passes.
+//!
+//! Every scene is rendered offscreen on a software adapter and compared against
+//! `tests/golden/<name>.png`. Set `SPINNY_UPDATE_GOLDEN=1` to (re)write the references.
+//! On a mismatch the rendered frame and a diff image are written to `target/golden-diff/`.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use winit::dpi::PhysicalSize;
+
+use spinny::model::MaterialParams;
+use spinny::texture::Texture;
+use spinny::vertex::Vertex;
+use spinny::{
+    create_plane, AlphaMode, BloomConfig, Camera, DirectionalLight, Error, Instance, Light,
+    Material, MaterialTextures, Mesh, Model, RenderConfig, Shading, ShadowFilter, SpotLight,
+    SsaoConfig, SsaoQuality, State, ToneMapper,
+};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+/// Largest per channel difference for a pixel to still count as matching.
+const CHANNEL_TOLERANCE: u8 = 8;
+/// Fraction of pixels allowed to exceed `CHANNEL_TOLERANCE`, absorbs rasterization differences
+/// between software adapters.
+const MAX_MISMATCHED_FRACTION: f32 = 0.002;
+
+// software adapters do not like being driven from several test threads at once
+static GPU: Mutex<()> = Mutex::new(());
+
+fn headless_state() -> Option<State> {
+    let state = pollster::block_on(State::new_headless(
+        PhysicalSize::new(WIDTH, HEIGHT),
+        RenderConfig {
+            force_fallback_adapter: true,
+            ..Default::default()
+        },
+    ));
+    return match state {
+        Ok(state) => Some(state),
+        Err(Error::NoAdapter) => {
+            eprintln!("no software adapter available, skipping golden image test");
+            None
+        }
+        Err(err) => panic!("{err}"),
+    };
+}
+
+fn fixed_camera(position: glam::Vec3, pitch: f32, yaw: f32) -> Camera {
+    let mut camera = Camera::new(
+        position,
+        f32::to_radians(pitch),
+        f32::to_radians(yaw),
+        2.5,
+        f32::to_radians(60.0),
+        WIDTH as f32 / HEIGHT as f32,
+    );
+    camera.update_view_matrix();
+    return camera;
+}
+
+/// Closed unit cube with outward facing normals, suitable for casting shadows.
+fn create_cube(state: &State, color: [u8; 4]) -> Model {
+    let faces = [
+        (glam::Vec3::X, glam::Vec3::Y),
+        (-glam::Vec3::X, glam::Vec3::Y),
+        (glam::Vec3::Y, glam::Vec3::Z),
+        (-glam::Vec3::Y, glam::Vec3::Z),
+        (glam::Vec3::Z, glam::Vec3::Y),
+        (-glam::Vec3::Z, glam::Vec3::Y),
+    ];
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for (normal, up) in faces {
+        let right = up.cross(normal);
+        let base = vertices.len() as u32;
+        for (u, v) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            vertices.push(Vertex {
+                pos: (normal + right * u + up * v).into(),
+                tex_cords: [(u + 1.0) / 2.0, (v + 1.0) / 2.0],
+                normals: normal.into(),
+                tangent: right.extend(-1.0).into(),
+            });
+        }
+        indices.extend([0, 1, 2, 2, 3, 0].iter().map(|i| base + i));
+    }
+    let (vertex_buffer, index_buffer) = Vertex::make_buffers(&state.device, &vertices, &indices);
+    let texture = Texture::create_solid_color_texture(&state.device, &state.queue, color);
+    return Model {
+        meshes: vec![Mesh {
+            vertex_buffer,
+            index_buffer,
+            len_indicies: indices.len(),
+            material_id: 0,
+        }],
+        materials: vec![Material::new(
+            &state.device,
+            &state.queue,
+            &state.layouts,
+            [0.5, 0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            32.0,
+            texture,
+        )],
+    };
+}
+
+fn floor(state: &State) -> (Model, Vec<Instance>) {
+    return (
+        create_plane(
+            &state.device,
+            &state.queue,
+            &state.layouts,
+            [124, 102, 92, 255],
+        ),
+        vec![Instance::from_translation_rotation_scale(
+            [0.0, -1.0, 0.0].into(),
+            glam::Quat::IDENTITY,
+            [1.0, 1.0, 1.0].into(),
+        )],
+    );
+}
+
+fn golden_dir() -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+}
+
+fn diff_dir() -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("target/golden-diff");
+}
+
+/// Returns the number of mismatched pixels and an image highlighting them in red.
+fn compare(expected: &image::RgbaImage, actual: &image::RgbaImage) -> (usize, image::RgbaImage) {
+    let mut mismatched = 0;
+    let diff = image::RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let a = actual.get_pixel(x, y);
+        let e = expected.get_pixel(x, y);
+        let max_delta = (0..4).map(|c| a[c].abs_diff(e[c])).max().unwrap();
+        if max_delta > CHANNEL_TOLERANCE {
+            mismatched += 1;
+            return image::Rgba([255, 0, 0, 255]);
+        }
+        let luma = (a[0] as u32 + a[1] as u32 + a[2] as u32) / 12;
+        return image::Rgba([luma as u8, luma as u8, luma as u8, 255]);
+    });
+    return (mismatched, diff);
+}
+
+fn assert_golden(name: &str, actual: &image::RgbaImage) {
+    let reference_path = golden_dir().join(format!("{name}.png"));
+    if std::env::var_os("SPINNY_UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save(&reference_path).unwrap();
+        eprintln!("wrote reference image {}", reference_path.display());
+        return;
+    }
+    assert!(
+        reference_path.exists(),
+        "{name}: missing {}, run with SPINNY_UPDATE_GOLDEN=1 to create it",
+        reference_path.display()
+    );
+    let expected = image::open(&reference_path).unwrap().to_rgba8();
+    assert_eq!(
+        expected.dimensions(),
+        actual.dimensions(),
+        "{name}: reference image has a different size"
+    );
+    let (mismatched, diff) = compare(&expected, actual);
+    let allowed = (MAX_MISMATCHED_FRACTION * (actual.width() * actual.height()) as f32) as usize;
+    if mismatched > allowed {
+        std::fs::create_dir_all(diff_dir()).unwrap();
+        let actual_path = diff_dir().join(format!("{name}.actual.png"));
+        let diff_path = diff_dir().join(format!("{name}.diff.png"));
+        actual.save(&actual_path).unwrap();
+        diff.save(&diff_path).unwrap();
+        panic!(
+            "{name}: {mismatched} pixels differ from {} (allowed {allowed}), see {} and {}",
+            reference_path.display(),
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+fn render_scene(name: &str, build: impl FnOnce(&mut State)) {
+    let _guard = GPU.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(mut state) = headless_state() else {
+        return;
+    };
+    build(&mut state);
+    let frame = state.render_to_image().unwrap();
+    assert_golden(name, &frame);
+}
+
+#[test]
+fn planes_blinn_phong() {
+    render_scene("planes_blinn_phong", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 1.0, 5.0), 0.0, -90.0);
+        state.scene.lights[0].pos = [-1.0, 3.0, 1.0];
+        state.scene.models.push(floor(state));
+        let wall = create_plane(
+            &state.device,
+            &state.queue,
+            &state.layouts,
+            [87, 212, 97, 255],
+        );
+        state.scene.models.push((
+            wall,
+            vec![Instance::from_translation_rotation_scale(
+                [0.0, 0.0, -5.0].into(),
+                glam::Quat::from_rotation_x(f32::to_radians(90.0)),
+                [1.0, 1.0, 1.0].into(),
+            )],
+        ));
+    });
+}
+
+/// A floating and a standing cube casting shadows onto the floor.
+fn cube_scene(state: &mut State) {
+    state.camera = fixed_camera(glam::vec3(0.0, 3.0, 6.0), -25.0, -90.0);
+    state.scene.lights[0].pos = [1.0, 5.0, 1.0];
+    state.scene.models.push(floor(state));
+    let cube = create_cube(state, [200, 60, 60, 255]);
+    state.scene.models.push((
+        cube,
+        vec![
+            Instance::from_translation_rotation_scale(
+                [0.0, 0.5, 0.0].into(),
+                glam::Quat::from_rotation_y(f32::to_radians(30.0)),
+                [0.5, 0.5, 0.5].into(),
+            ),
+            Instance::from_translation_rotation_scale(
+                [-2.0, 0.0, -1.0].into(),
+                glam::Quat::IDENTITY,
+                [0.4, 1.0, 0.4].into(),
+            ),
+        ],
+    ));
+}
+
+#[test]
+fn cube_shadows() {
+    render_scene("cube_shadows", cube_scene);
+}
+
+#[test]
+fn cube_shadows_pcf() {
+    render_scene("cube_shadows_pcf", |state| {
+        cube_scene(state);
+        state.config.shadows.filter = ShadowFilter::Pcf;
+        state.config.shadows.pcf_radius = 3.0;
+    });
+}
+
+#[test]
+fn cube_shadows_pcss() {
+    render_scene("cube_shadows_pcss", |state| {
+        cube_scene(state);
+        state.config.shadows.filter = ShadowFilter::Pcss;
+        state.config.shadows.light_size = 0.3;
+    });
+}
+
+#[test]
+fn light_cube() {
+    render_scene("light_cube", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 1.0, 3.0), 0.0, -90.0);
+        state.scene.lights[0].pos = [0.0, 1.0, 0.0];
+        state.scene.lights[0].color = [1.0, 0.8, 0.6];
+        state.scene.models.push(floor(state));
+    });
+}
+
+#[test]
+fn resized_target() {
+    render_scene("resized_target", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 3.0, 6.0), -25.0, -90.0);
+        state.scene.lights[0].pos = [1.0, 5.0, 1.0];
+        state.scene.models.push(floor(state));
+        let cube = create_cube(state, [60, 60, 200, 255]);
+        state.scene.models.push((
+            cube,
+            vec![Instance::from_translation_rotation_scale(
+                [0.0, 0.5, 0.0].into(),
+                glam::Quat::IDENTITY,
+                [0.5, 0.5, 0.5].into(),
+            )],
+        ));
+        // minimised targets skip frames instead of failing validation
+        state.resize(PhysicalSize::new(0, 0));
+        state.render().unwrap();
+        state.resize(PhysicalSize::new(200, 300));
+    });
+}
+
+/// Every draw in the main pass has to see its own material, not the last one written.
+#[test]
+fn materials_per_draw() {
+    let _guard = GPU.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(mut state) = headless_state() else {
+        return;
+    };
+    state.camera = fixed_camera(glam::vec3(0.0, 0.0, 4.0), 0.0, -90.0);
+    state.scene.lights[0].pos = [0.0, 0.0, 3.0];
+    for (x, diffuse) in [(-1.2, [1.0, 0.0, 0.0]), (1.2, [0.0, 0.0, 1.0])] {
+        let mut cube = create_cube(&state, [255, 255, 255, 255]);
+        let params = MaterialParams {
+            ambient: [0.0, 0.0, 0.0],
+            diffuse,
+            specular: [0.0, 0.0, 0.0],
+            ..cube.materials[0].params
+        };
+        cube.materials[0].set_params(&state.queue, params);
+        state.scene.models.push((
+            cube,
+            vec![Instance::from_translation_rotation_scale(
+                [x, 0.0, 0.0].into(),
+                glam::Quat::IDENTITY,
+                [0.5, 0.5, 0.5].into(),
+            )],
+        ));
+    }
+    let frame = state.render_to_image().unwrap();
+    let left = frame.get_pixel(WIDTH / 4 + 10, HEIGHT / 2);
+    let right = frame.get_pixel(WIDTH * 3 / 4 - 10, HEIGHT / 2);
+    assert!(
+        left[0] > 100 && left[2] < 10,
+        "left cube should be red, got {left:?}"
+    );
+    assert!(
+        right[2] > 100 && right[0] < 10,
+        "right cube should be blue, got {right:?}"
+    );
+}
+
+#[test]
+fn two_lights() {
+    render_scene("two_lights", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 3.0, 6.0), -25.0, -90.0);
+        state.scene.lights[0].pos = [-2.0, 3.0, 1.0];
+        state.scene.lights[0].color = [1.0, 0.4, 0.4];
+        let mut blue = Light::new([2.0, 3.0, 1.0], [0.4, 0.4, 1.0]);
+        blue.range = 8.0;
+        state.scene.add_light(blue);
+        state.scene.models.push(floor(state));
+        let cube = create_cube(state, [220, 220, 220, 255]);
+        state.scene.models.push((
+            cube,
+            vec![Instance::from_translation_rotation_scale(
+                [0.0, 0.0, 0.0].into(),
+                glam::Quat::IDENTITY,
+                [0.5, 1.0, 0.5].into(),
+            )],
+        ));
+    });
+}
+
+/// Cubes receding from the camera on a long floor, lit only by a directional light.
+fn sun_scene(state: &mut State) {
+    state.camera = fixed_camera(glam::vec3(0.0, 2.0, 6.0), -15.0, -90.0);
+    state.scene.lights.clear();
+    state.scene.sun = Some(DirectionalLight::new([-0.4, -1.0, -0.6], [1.0, 0.95, 0.9]));
+    let (floor, _) = floor(state);
+    state.scene.models.push((
+        floor,
+        vec![Instance::from_translation_rotation_scale(
+            [0.0, -1.0, -20.0].into(),
+            glam::Quat::IDENTITY,
+            [30.0, 1.0, 30.0].into(),
+        )],
+    ));
+    let cube = create_cube(state, [220, 220, 220, 255]);
+    let instances = [(0.0, 2.0), (1.5, -4.0), (-2.0, -12.0), (3.0, -25.0)].map(|(x, z)| {
+        Instance::from_translation_rotation_scale(
+            [x, 0.0, z].into(),
+            glam::Quat::from_rotation_y(f32::to_radians(30.0)),
+            [0.5, 1.0, 0.5].into(),
+        )
+    });
+    state.scene.models.push((cube, instances.to_vec()));
+}
+
+#[test]
+fn sun_cascades() {
+    render_scene("sun_cascades", sun_scene);
+}
+
+#[test]
+fn sun_cascades_debug() {
+    render_scene("sun_cascades_debug", |state| {
+        sun_scene(state);
+        state.config.cascades.count = 3;
+        state.config.cascades.debug = true;
+    });
+}
+
+#[test]
+fn spot_light() {
+    render_scene("spot_light", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 3.0, 6.0), -25.0, -90.0);
+        state.scene.lights.clear();
+        let mut spot = SpotLight::new([1.5, 3.0, 1.0], [-0.5, -1.0, -0.4], [1.0, 0.9, 0.7]);
+        spot.range = 10.0;
+        state.scene.add_spot_light(spot);
+        state.scene.models.push(floor(state));
+        let cube = create_cube(state, [220, 220, 220, 255]);
+        state.scene.models.push((
+            cube,
+            vec![Instance::from_translation_rotation_scale(
+                [0.0, -0.5, 0.0].into(),
+                glam::Quat::from_rotation_y(f32::to_radians(30.0)),
+                [0.4, 0.5, 0.4].into(),
+            )],
+        ));
+    });
+}
+
+/// A light further away than the old fixed 100 unit shadow far plane still casts shadows.
+#[test]
+fn distant_light_shadows() {
+    render_scene("distant_light_shadows", |state| {
+        cube_scene(state);
+        let light = &mut state.scene.lights[0];
+        light.pos = [60.0, 150.0, 40.0];
+        light.range = 400.0;
+        light.shadow.near = 1.0;
+        // a shadow map texel is around 0.3 units wide this far from the light
+        light.shadow.depth_bias = 0.3;
+        light.shadow.normal_bias = 0.3;
+    });
+}
+
+/// `cube_scene` with a light bright enough to clip without tonemapping.
+fn bright_cube_scene(state: &mut State) {
+    cube_scene(state);
+    state.scene.lights[0].intensity = 6.0;
+}
+
+#[test]
+fn tonemap_reinhard() {
+    render_scene("tonemap_reinhard", |state| {
+        bright_cube_scene(state);
+        state.config.tonemapping.operator = ToneMapper::Reinhard;
+    });
+}
+
+#[test]
+fn tonemap_aces() {
+    render_scene("tonemap_aces", |state| {
+        bright_cube_scene(state);
+        state.config.tonemapping.operator = ToneMapper::Aces;
+    });
+}
+
+#[test]
+fn tonemap_agx() {
+    render_scene("tonemap_agx", |state| {
+        bright_cube_scene(state);
+        state.config.tonemapping.operator = ToneMapper::Agx;
+    });
+}
+
+/// The first frame is exposed for the measured luminance without adapting over time.
+#[test]
+fn auto_exposure() {
+    render_scene("auto_exposure", |state| {
+        bright_cube_scene(state);
+        state.config.tonemapping.operator = ToneMapper::Aces;
+        state.config.tonemapping.auto_exposure = true;
+    });
+}
+
+/// A light cube bright enough to glow over the floor behind it.
+#[test]
+fn bloom() {
+    render_scene("bloom", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 1.0, 3.0), 0.0, -90.0);
+        state.scene.lights[0].pos = [0.0, 1.0, 0.0];
+        state.scene.lights[0].color = [1.0, 0.8, 0.6];
+        state.scene.lights[0].intensity = 4.0;
+        state.scene.models.push(floor(state));
+        state.config.tonemapping.operator = ToneMapper::Aces;
+        state.config.bloom = BloomConfig {
+            enabled: true,
+            intensity: 0.2,
+            ..Default::default()
+        };
+    });
+}
+
+/// Cubes resting on the floor, darkened by ambient occlusion where they touch it.
+fn contact_scene(state: &mut State) {
+    state.camera = fixed_camera(glam::vec3(0.0, 2.0, 4.0), -25.0, -90.0);
+    state.scene.lights[0].pos = [3.0, 5.0, 4.0];
+    state.scene.models.push(floor(state));
+    let cube = create_cube(state, [220, 220, 220, 255]);
+    state.scene.models.push((
+        cube,
+        vec![
+            Instance::from_translation_rotation_scale(
+                [-0.8, -0.5, 0.0].into(),
+                glam::Quat::from_rotation_y(f32::to_radians(20.0)),
+                [0.5, 0.5, 0.5].into(),
+            ),
+            Instance::from_translation_rotation_scale(
+                [0.6, -0.6, -0.3].into(),
+                glam::Quat::IDENTITY,
+                [0.4, 0.4, 0.4].into(),
+            ),
+        ],
+    ));
+    state.config.ssao = SsaoConfig {
+        enabled: true,
+        quality: SsaoQuality::High,
+        intensity: 2.0,
+        ..Default::default()
+    };
+}
+
+#[test]
+fn ssao() {
+    render_scene("ssao", contact_scene);
+}
+
+#[test]
+fn ssao_debug() {
+    render_scene("ssao_debug", |state| {
+        contact_scene(state);
+        state.config.ssao.debug = true;
+    });
+}
+
+/// Cube with a PBR material, textured white so only `params` shows.
+fn create_pbr_cube(state: &State, params: MaterialParams) -> Model {
+    let mut cube = create_cube(state, [255, 255, 255, 255]);
+    let white = Texture::create_solid_color_texture(&state.device, &state.queue, [255; 4]);
+    cube.materials[0] = Material::with_textures(
+        &state.device,
+        &state.layouts,
+        Shading::Pbr,
+        params,
+        MaterialTextures::new(&state.device, &state.queue, white),
+    );
+    return cube;
+}
+
+/// Rough and polished plastic, gold and an emissive cube on a Blinn-Phong floor.
+#[test]
+fn pbr_materials() {
+    render_scene("pbr_materials", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 2.0, 5.0), -20.0, -90.0);
+        state.scene.lights[0].pos = [0.0, 4.5, -5.0];
+        state.scene.models.push(floor(state));
+        let materials = [
+            MaterialParams {
+                base_color: [0.8, 0.1, 0.1, 1.0],
+                roughness: 0.9,
+                ..Default::default()
+            },
+            MaterialParams {
+                base_color: [0.8, 0.1, 0.1, 1.0],
+                roughness: 0.2,
+                ..Default::default()
+            },
+            MaterialParams {
+                base_color: [1.0, 0.77, 0.34, 1.0],
+                metallic: 1.0,
+                roughness: 0.4,
+                ..Default::default()
+            },
+            MaterialParams {
+                base_color: [0.1, 0.1, 0.1, 1.0],
+                emissive: [0.2, 0.6, 1.0],
+                ..Default::default()
+            },
+        ];
+        for (i, params) in materials.into_iter().enumerate() {
+            let cube = create_pbr_cube(state, params);
+            state.scene.models.push((
+                cube,
+                vec![Instance::from_translation_rotation_scale(
+                    [-2.25 + 1.5 * i as f32, -0.6, 0.0].into(),
+                    glam::Quat::from_rotation_y(f32::to_radians(30.0)),
+                    [0.4, 0.4, 0.4].into(),
+                )],
+            ));
+        }
+    });
+}
+
+/// Ridges running along v, as a tangent space normal map.
+fn ridged_normal_map(state: &State) -> Texture {
+    let image = image::RgbaImage::from_fn(64, 64, |x, _| {
+        let slope = (x as f32 / 64.0 * std::f32::consts::TAU * 4.0).cos() * 0.8;
+        let normal = glam::vec3(slope, 0.0, 1.0).normalize();
+        let [r, g, b] = (normal * 0.5 + 0.5)
+            .to_array()
+            .map(|c| (c * 255.0).round() as u8);
+        return image::Rgba([r, g, b, 255]);
+    });
+    return Texture::load_texture_with_format(
+        &state.device,
+        &state.queue,
+        &state.mipmaps,
+        &image,
+        wgpu::TextureFormat::Rgba8Unorm,
+    );
+}
+
+/// A light grazing ridged faces of a Blinn-Phong cube.
+#[test]
+fn normal_map() {
+    render_scene("normal_map", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 1.5, 3.5), -20.0, -90.0);
+        state.scene.lights[0].pos = [-2.5, 2.0, 1.0];
+        state.scene.models.push(floor(state));
+        let mut cube = create_cube(state, [200, 200, 200, 255]);
+        let mut textures = MaterialTextures::new(
+            &state.device,
+            &state.queue,
+            Texture::create_solid_color_texture(&state.device, &state.queue, [200, 200, 200, 255]),
+        );
+        textures.normal = ridged_normal_map(state);
+        cube.materials[0] = Material::with_textures(
+            &state.device,
+            &state.layouts,
+            Shading::Phong,
+            cube.materials[0].params,
+            textures,
+        );
+        state.scene.models.push((
+            cube,
+            vec![Instance::from_translation_rotation_scale(
+                [0.0, 0.0, 0.0].into(),
+                glam::Quat::from_rotation_y(f32::to_radians(30.0)),
+                [0.7, 0.7, 0.7].into(),
+            )],
+        ));
+    });
+}
+
+/// Stripes of `stripe` over `background`, running along v.
+fn striped_texture(state: &State, stripe: [u8; 4], background: [u8; 4]) -> Texture {
+    let image = image::RgbaImage::from_fn(64, 64, |x, _| match (x / 8) % 2 {
+        0 => image::Rgba(stripe),
+        _ => image::Rgba(background),
+    });
+    return Texture::load_texture(&state.device, &state.queue, &state.mipmaps, &image);
+}
+
+/// A Blinn-Phong cube shiny only along specular stripes, with glowing emissive stripes on top.
+#[test]
+fn phong_maps() {
+    render_scene("phong_maps", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 1.5, 3.5), -20.0, -90.0);
+        state.scene.lights[0].pos = [0.5, 3.0, 2.5];
+        state.scene.models.push(floor(state));
+        let mut cube = create_cube(state, [255, 255, 255, 255]);
+        let mut textures = MaterialTextures::new(
+            &state.device,
+            &state.queue,
+            Texture::create_solid_color_texture(&state.device, &state.queue, [255, 255, 255, 255]),
+        );
+        textures.specular = striped_texture(state, [255, 255, 255, 255], [0, 0, 0, 255]);
+        textures.emissive = striped_texture(state, [0, 0, 0, 255], [255, 80, 0, 255]);
+        let params = MaterialParams {
+            diffuse: [0.2, 0.2, 0.2],
+            specular: [1.0, 1.0, 1.0],
+            shininess: 4.0,
+            emissive: [0.5, 0.5, 0.5],
+            ..cube.materials[0].params
+        };
+        cube.materials[0] = Material::with_textures(
+            &state.device,
+            &state.layouts,
+            Shading::Phong,
+            params,
+            textures,
+        );
+        state.scene.models.push((
+            cube,
+            vec![Instance::from_translation_rotation_scale(
+                [0.0, 0.0, 0.0].into(),
+                glam::Quat::from_rotation_y(f32::to_radians(30.0)),
+                [0.7, 0.7, 0.7].into(),
+            )],
+        ));
+    });
+}
+
+/// Cube of `color` with a Blinn-Phong material drawn in `alpha_mode`.
+fn create_alpha_cube(
+    state: &State,
+    color: [u8; 4],
+    alpha_mode: AlphaMode,
+    params: MaterialParams,
+) -> Model {
+    let mut cube = create_cube(state, color);
+    cube.materials[0].set_params(&state.queue, params);
+    cube.materials[0].alpha_mode = alpha_mode;
+    return cube;
+}
+
+/// Half transparent red and blue cubes in front of an opaque one, the nearer red cube added
+/// first so only sorting puts it in front.
+#[test]
+fn transparent_sorted() {
+    render_scene("transparent_sorted", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 1.5, 5.0), -15.0, -90.0);
+        state.scene.lights[0].pos = [1.0, 4.0, 3.0];
+        state.scene.models.push(floor(state));
+        let translucent = MaterialParams {
+            dissolve: 0.5,
+            ..MaterialParams::default()
+        };
+        let opaque = create_cube(state, [220, 220, 220, 255]);
+        let red = create_alpha_cube(state, [255, 40, 40, 255], AlphaMode::Blend, translucent);
+        let blue = create_alpha_cube(state, [40, 40, 255, 255], AlphaMode::Blend, translucent);
+        for (model, translation) in [
+            (red, [0.3, -0.5, 1.2]),
+            (blue, [-0.3, -0.5, 0.0]),
+            (opaque, [-0.6, -0.5, -1.5]),
+        ] {
+            state.scene.models.push((
+                model,
+                vec![Instance::from_translation_rotation_scale(
+                    translation.into(),
+                    glam::Quat::IDENTITY,
+                    [0.5, 0.5, 0.5].into(),
+                )],
+            ));
+        }
+    });
+}
+
+/// A striped cutout panel lit by a point light and the sun, both shadows striped as well.
+#[test]
+fn alpha_cutout() {
+    render_scene("alpha_cutout", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 1.5, 4.0), -20.0, -90.0);
+        state.scene.lights[0].pos = [-1.5, 2.0, -2.0];
+        state.scene.sun = Some(DirectionalLight::new([0.6, -1.0, -0.4], [0.5, 0.5, 0.5]));
+        state.scene.models.push(floor(state));
+        let mut panel = create_cube(state, [90, 200, 90, 255]);
+        let mut textures = MaterialTextures::new(
+            &state.device,
+            &state.queue,
+            Texture::create_solid_color_texture(&state.device, &state.queue, [90, 200, 90, 255]),
+        );
+        textures.alpha = striped_texture(state, [255, 255, 255, 255], [0, 0, 0, 255]);
+        let params = MaterialParams {
+            alpha_cutoff: 0.5,
+            ..panel.materials[0].params
+        };
+        panel.materials[0] = Material::with_textures(
+            &state.device,
+            &state.layouts,
+            Shading::Phong,
+            params,
+            textures,
+        );
+        panel.materials[0].alpha_mode = AlphaMode::Mask;
+        state.scene.models.push((
+            panel,
+            vec![Instance::from_translation_rotation_scale(
+                [0.0, 0.0, 0.0].into(),
+                glam::Quat::from_rotation_y(f32::to_radians(20.0)),
+                [0.8, 0.8, 0.05].into(),
+            )],
+        ));
+    });
+}
+
+/// A wide floor quad with texture coordinates repeating `repeats` times across it.
+fn tiled_floor(state: &State, texture: Texture, repeats: f32) -> Model {
+    let vertices: Vec<Vertex> = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+        .iter()
+        .map(|&(x, z)| Vertex {
+            pos: [x * 20.0, 0.0, z * 20.0],
+            tex_cords: [(x + 1.0) / 2.0 * repeats, (z + 1.0) / 2.0 * repeats],
+            normals: [0.0, 1.0, 0.0],
+            tangent: [1.0, 0.0, 0.0, 1.0],
+        })
+        .collect();
+    let indices = [0, 2, 1, 2, 0, 3];
+    let (vertex_buffer, index_buffer) = Vertex::make_buffers(&state.device, &vertices, &indices);
+    return Model {
+        meshes: vec![Mesh {
+            vertex_buffer,
+            index_buffer,
+            len_indicies: indices.len(),
+            material_id: 0,
+        }],
+        materials: vec![Material::new(
+            &state.device,
+            &state.queue,
+            &state.layouts,
+            [0.5, 0.5, 0.5],
+            [0.5, 0.5, 0.5],
+            [0.0, 0.0, 0.0],
+            32.0,
+            texture,
+        )],
+    };
+}
+
+/// A repeating checkerboard seen at a grazing angle, mipmaps and anisotropic filtering fade it
+/// to grey in the distance instead of aliasing.
+#[test]
+fn tiled_texture() {
+    render_scene("tiled_texture", |state| {
+        state.camera = fixed_camera(glam::vec3(0.0, 0.6, 4.0), -8.0, -90.0);
+        state.scene.lights[0].pos = [0.0, 8.0, 0.0];
+        let checker = image::RgbaImage::from_fn(64, 64, |x, y| match (x / 32 + y / 32) % 2 {
+            0 => image::Rgba([230, 230, 230, 255]),
+            _ => image::Rgba([30, 30, 30, 255]),
+        });
+        let texture = Texture::load_texture(&state.device, &state.queue, &state.mipmaps, &checker);
+        assert_eq!(texture.texture.mip_level_count(), 7);
+        // solid colour textures have no mip chain, only the checker needed a pipeline
+        assert_eq!(state.mipmaps.pipelines.borrow().len(), 1);
+        state.scene.models.push((
+            tiled_floor(state, texture, 40.0),
+            vec![Instance::from_translation_rotation_scale(
+                [0.0, -1.0, 0.0].into(),
+                glam::Quat::IDENTITY,
+                [1.0, 1.0, 1.0].into(),
+            )],
+        ));
+    });
+}